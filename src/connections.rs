@@ -0,0 +1,163 @@
+use std::net::IpAddr;
+
+use tabled::{Table, Tabled};
+
+use crate::process_table::ProcessTable;
+use crate::procfs::{self, SocketEntry};
+use crate::{format_socket_address, hidden_command, hidden_process_name, privileged, truncate_string, PortSpec, ProcessInfo, Protocol};
+
+/// One end of an established TCP connection that involves the requested port
+#[derive(Tabled, serde::Serialize)]
+struct ConnectionInfo {
+    /// "in" for connections accepted on the port, "out" for local clients connecting to it
+    direction: String,
+    local: String,
+    remote: String,
+    pid: String,
+    process_name: String,
+    command: String,
+}
+
+/// Reads established TCP connections from the kernel, through netlink or /proc
+fn scan_established() -> Option<Vec<SocketEntry>> {
+    #[cfg(target_os = "linux")]
+    if let Some(sockets) = crate::netlink::dump_established() {
+        return Some(sockets);
+    }
+
+    procfs::read_established_sockets()
+}
+
+/// Fills in how many clients are connected to each TCP listener
+pub fn count_clients(processes: &mut [ProcessInfo]) {
+    let Some(connections) = scan_established() else {
+        return;
+    };
+
+    for process in processes.iter_mut().filter(|p| p.protocol == Protocol::Tcp) {
+        let (Ok(address), Ok(port)) = (process.address.parse::<IpAddr>(), process.port.parse::<u16>()) else {
+            continue;
+        };
+
+        let count = connections
+            .iter()
+            .filter(|connection| connection.port == port && accepted_by(address, connection.address))
+            .count();
+        process.clients = Some(count);
+    }
+}
+
+fn accepted_by(listen_address: IpAddr, local_address: IpAddr) -> bool {
+    if listen_address.is_unspecified() {
+        // IPv4 clients of a dual-stack listener get IPv4-mapped IPv6 sockets, so
+        // comparing families keeps 0.0.0.0 and [::] listeners on one port apart
+        listen_address.is_ipv4() == local_address.is_ipv4()
+    } else {
+        listen_address == local_address
+    }
+}
+
+/// Lists established connections to or from the given port with the local process on each end
+pub fn list_connections(port: &PortSpec, sudo: bool, simple: bool, json_output: bool) {
+    if port.protocol.is_some_and(|protocol| protocol != Protocol::Tcp) {
+        println!("Connections can only be listed for TCP ports.");
+        return;
+    }
+
+    let Some(sockets) = scan_established() else {
+        println!("Listing connections needs netlink or /proc/net (Linux only).");
+        return;
+    };
+
+    let owners = procfs::map_socket_inodes_to_pids();
+    let mut matching: Vec<(&str, &SocketEntry, Option<u32>)> = sockets
+        .iter()
+        .filter_map(|socket| {
+            let direction = if port.matches_socket(socket.address, socket.port) {
+                "in"
+            } else if port.matches_socket(socket.remote_address, socket.remote_port) {
+                "out"
+            } else {
+                return None;
+            };
+            let pid = owners.get(&socket.inode).and_then(|pids| pids.first()).copied();
+            Some((direction, socket, pid))
+        })
+        .collect();
+
+    // Sockets of other users' processes, looked up again as root with --sudo
+    let hidden = matching.iter().filter(|&&(_, _, pid)| pid.is_none()).count();
+    if sudo && hidden > 0 && !privileged::is_root() {
+        let noun = if hidden == 1 { "connection" } else { "connections" };
+        let reason = format!("look up the process of {} hidden {}", hidden, noun);
+        if let Some(owners) = privileged::socket_owners_with_sudo(&reason) {
+            for (_, socket, pid) in matching.iter_mut().filter(|(_, _, pid)| pid.is_none()) {
+                *pid = owners.get(&socket.inode).and_then(|pids| pids.first()).copied();
+            }
+        }
+    }
+
+    let pids: Vec<u32> = matching.iter().filter_map(|&(_, _, pid)| pid).collect();
+    let table = ProcessTable::load(&pids);
+    let mut connections: Vec<ConnectionInfo> = matching
+        .into_iter()
+        .map(|(direction, socket, pid)| {
+            let (pid, process_name, command) = match pid {
+                Some(pid) => match table.get(pid) {
+                    Some(meta) => (pid.to_string(), meta.comm.clone(), meta.cmdline.clone()),
+                    None => (pid.to_string(), "unknown".to_string(), String::new()),
+                },
+                None => ("hidden".to_string(), hidden_process_name().to_string(), hidden_command(sudo).to_string()),
+            };
+
+            ConnectionInfo {
+                direction: direction.to_string(),
+                local: format_socket_address(&socket.address.to_canonical().to_string(), &socket.port.to_string()),
+                remote: format_socket_address(
+                    &socket.remote_address.to_canonical().to_string(),
+                    &socket.remote_port.to_string(),
+                ),
+                pid,
+                process_name,
+                command,
+            }
+        })
+        .collect();
+
+    // Accepted connections first, then the local clients
+    connections.sort_by(|a, b| a.direction.cmp(&b.direction));
+
+    if connections.is_empty() {
+        println!("No established connections on {}", port.describe());
+        return;
+    }
+
+    if json_output {
+        match serde_json::to_string_pretty(&connections) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("Error serializing to JSON: {}", e),
+        }
+    } else if simple {
+        for connection in &connections {
+            let arrow = if connection.direction == "in" { "<-" } else { "->" };
+            println!(
+                "{} {} {} {} ({})",
+                connection.local,
+                arrow,
+                connection.remote,
+                connection.pid,
+                truncate_string(&connection.process_name, 15)
+            );
+        }
+    } else {
+        let rows: Vec<ConnectionInfo> = connections
+            .into_iter()
+            .map(|c| ConnectionInfo {
+                process_name: truncate_string(&c.process_name, 12),
+                command: truncate_string(&c.command, 40),
+                ..c
+            })
+            .collect();
+        println!("{}", Table::new(rows));
+    }
+}
@@ -0,0 +1,659 @@
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::process::{Command as StdCommand, Stdio};
+use std::time::Duration;
+
+use crate::{format_socket_address, podman};
+
+/// Where the daemon listens unless DOCKER_HOST says otherwise
+const DEFAULT_HOST: &str = "unix:///var/run/docker.sock";
+/// Listing and inspecting answer quickly; stopping waits for the container's own
+/// shutdown timeout (10s by default) before the daemon replies
+pub(crate) const READ_TIMEOUT: Duration = Duration::from_secs(5);
+pub(crate) const STOP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The container engine a container runs under. Podman has no shared daemon: rootful
+/// containers belong to root's instance, rootless ones to their user's.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Engine {
+    Docker,
+    Podman { uid: u32 },
+}
+
+impl Engine {
+    pub fn name(self) -> &'static str {
+        match self {
+            Engine::Docker => "Docker",
+            Engine::Podman { .. } => "Podman",
+        }
+    }
+
+    /// The engine's CLI, for the commands shown to the user
+    pub fn program(self) -> &'static str {
+        match self {
+            Engine::Docker => "docker",
+            Engine::Podman { .. } => "podman",
+        }
+    }
+
+    /// Where the engine's API listens: DOCKER_HOST or the default Docker socket, or the
+    /// Podman socket of the instance's user
+    fn host(self) -> String {
+        match self {
+            Engine::Docker => {
+                env::var("DOCKER_HOST").ok().filter(|host| !host.is_empty()).unwrap_or_else(|| DEFAULT_HOST.to_string())
+            }
+            Engine::Podman { uid } => podman::host(uid),
+        }
+    }
+}
+
+impl fmt::Display for Engine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Engine::Docker | Engine::Podman { uid: 0 } => f.write_str(self.name()),
+            Engine::Podman { uid } => write!(f, "rootless Podman (UID {})", uid),
+        }
+    }
+}
+
+impl serde::Serialize for Engine {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.program())
+    }
+}
+
+/// Every engine that may be running containers: Docker, and the Podman instances of root
+/// and of the users with containers running
+pub(crate) fn engines() -> Vec<Engine> {
+    let mut engines = vec![Engine::Docker];
+    engines.extend(podman::instances().into_iter().map(|uid| Engine::Podman { uid }));
+    engines
+}
+
+/// A running container, as the Engine API's container list describes it
+#[derive(Clone)]
+pub(crate) struct Container {
+    pub engine: Engine,
+    pub id: String,
+    /// Without the leading slash the API puts on names
+    pub name: String,
+    pub image: String,
+    /// Its address on each network it is attached to
+    pub ips: Vec<String>,
+    pub ports: Vec<PortBinding>,
+    pub labels: BTreeMap<String, String>,
+    /// The Podman pod the container belongs to. A pod's ports are published by its infra
+    /// container, so freeing them means stopping the whole pod.
+    pub pod: Option<String>,
+}
+
+impl Container {
+    /// The 12-character ID `docker ps` shows
+    pub fn short_id(&self) -> &str {
+        &self.id[..self.id.len().min(12)]
+    }
+
+    /// The command that removes the container once stopped, or its pod
+    pub fn remove_command(&self) -> String {
+        match &self.pod {
+            Some(pod) => format!("{} pod rm {}", self.engine.program(), pod),
+            None => format!("{} rm {}", self.engine.program(), self.short_id()),
+        }
+    }
+
+    /// The project and service, for containers started by `docker compose` (or
+    /// `podman compose`, which sets the same labels)
+    pub fn compose(&self) -> Option<ComposeService> {
+        let label = |key: &str| self.labels.get(key).filter(|value| !value.is_empty()).cloned();
+        Some(ComposeService {
+            program: self.engine.program(),
+            project: label("com.docker.compose.project")?,
+            service: label("com.docker.compose.service")?,
+            working_dir: label("com.docker.compose.project.working_dir"),
+        })
+    }
+}
+
+/// Where a container comes from in a Compose project, read from the labels Compose puts on it
+pub(crate) struct ComposeService {
+    /// `docker` or `podman`, whichever runs the containers
+    pub program: &'static str,
+    pub project: String,
+    pub service: String,
+    /// The directory `docker compose up` ran in, holding the compose file
+    pub working_dir: Option<String>,
+}
+
+impl ComposeService {
+    /// `docker compose stop` for the service, or for every service in the project
+    pub fn stop_command(&self, whole_project: bool) -> Vec<String> {
+        let mut args: Vec<String> = [self.program, "compose", "--project-name", &self.project]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        args.push("stop".to_string());
+        if !whole_project {
+            args.push(self.service.clone());
+        }
+        args
+    }
+
+    /// Runs a `docker compose` command from the project's working directory, where Compose
+    /// finds the compose file and its .env. Needs the docker CLI with the compose plugin,
+    /// or podman with a compose provider.
+    pub fn run(&self, args: &[String]) -> Result<(), String> {
+        let mut command = StdCommand::new(&args[0]);
+        command.args(&args[1..]);
+        if let Some(dir) = self.working_dir.as_ref().filter(|dir| Path::new(dir).is_dir()) {
+            command.current_dir(dir);
+        }
+        let output = command.stdin(Stdio::null()).output().map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => format!("{} is not installed", args[0]),
+            _ => e.to_string(),
+        })?;
+        if output.status.success() {
+            return Ok(());
+        }
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// One port of a container, published on the host or only exposed
+#[derive(Clone)]
+pub(crate) struct PortBinding {
+    pub host_ip: Option<String>,
+    pub host_port: Option<u16>,
+    pub container_port: u16,
+    pub protocol: String,
+}
+
+impl fmt::Display for PortBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.host_ip, self.host_port) {
+            (Some(ip), Some(port)) => {
+                let host = format_socket_address(ip, &port.to_string());
+                write!(f, "{}->{}/{}", host, self.container_port, self.protocol)
+            }
+            (None, Some(port)) => write!(f, "{}->{}/{}", port, self.container_port, self.protocol),
+            _ => write!(f, "{}/{}", self.container_port, self.protocol),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) enum EngineError {
+    /// Nothing to talk to: no daemon or API socket, or a DOCKER_HOST this client can't reach
+    Unavailable { engine: Engine, reason: String },
+    /// Docker's socket belongs to root and the docker group, rootful Podman's to root alone
+    PermissionDenied { engine: Engine, host: String },
+    /// The daemon answered with an error
+    Api { status: u16, message: String },
+    /// The CLI, standing in for an API socket that is off, failed
+    Failed(String),
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::Unavailable { engine, reason } => write!(f, "{} is not reachable: {}", engine, reason),
+            EngineError::PermissionDenied { engine: Engine::Docker, host } => {
+                write!(f, "permission denied on {} (are you in the docker group?)", host)
+            }
+            EngineError::PermissionDenied { engine, host } => {
+                write!(f, "permission denied on {} ({} containers need its user or root)", host, engine)
+            }
+            EngineError::Api { status, message } => write!(f, "{} (HTTP {})", message, status),
+            EngineError::Failed(message) => f.write_str(message),
+        }
+    }
+}
+
+/// Every running container of one engine, listed once per scan and shared by all the rows
+/// that need it: each forwarder row, published port and kill would otherwise ask again
+pub(crate) struct ContainerSnapshot {
+    containers: Vec<Container>,
+    /// Host ports published by the containers, from their port bindings
+    /// (`NetworkSettings.Ports`, which the container list reports as `Ports`):
+    /// (protocol, host port) -> (container, binding) indices
+    published: HashMap<(String, u16), Vec<(usize, usize)>>,
+}
+
+/// The container snapshots of one scan, one per engine, each taken the first time a row
+/// needs it. An engine that can't be reached isn't asked again.
+#[derive(Default)]
+pub(crate) struct Snapshots {
+    taken: Vec<(Engine, Result<ContainerSnapshot, EngineError>)>,
+    /// Only ask the engines' API sockets, never the podman CLI in place of one that is off
+    api_only: bool,
+}
+
+impl Snapshots {
+    /// Snapshots for a plain listing, which has every hidden row to name and shouldn't run
+    /// `podman ps` for them on each call. -p and -k may still fall back on the CLI.
+    pub fn api_only() -> Snapshots {
+        Snapshots { taken: Vec::new(), api_only: true }
+    }
+
+    pub fn get(&mut self, engine: Engine) -> Result<&ContainerSnapshot, EngineError> {
+        let index = self.take(engine);
+        self.taken[index].1.as_ref().map_err(EngineError::clone)
+    }
+
+    /// The snapshots of every engine that answered
+    pub fn all(&mut self) -> Vec<&ContainerSnapshot> {
+        for engine in engines() {
+            self.take(engine);
+        }
+        self.taken.iter().filter_map(|(_, snapshot)| snapshot.as_ref().ok()).collect()
+    }
+
+    fn take(&mut self, engine: Engine) -> usize {
+        if let Some(index) = self.taken.iter().position(|(taken, _)| *taken == engine) {
+            return index;
+        }
+        self.taken.push((engine, ContainerSnapshot::load(engine, !self.api_only)));
+        self.taken.len() - 1
+    }
+}
+
+impl ContainerSnapshot {
+    pub fn load(engine: Engine, cli: bool) -> Result<ContainerSnapshot, EngineError> {
+        let containers = match engine {
+            Engine::Docker => {
+                let body = request(engine, "GET", "/containers/json", READ_TIMEOUT)?;
+                let containers: Vec<ApiContainer> = serde_json::from_slice(&body).map_err(|e| EngineError::Unavailable {
+                    engine,
+                    reason: format!("unexpected answer from the daemon: {}", e),
+                })?;
+                containers.into_iter().map(Container::from).collect()
+            }
+            Engine::Podman { uid } => podman::list_containers(uid, cli)?,
+        };
+
+        let mut published: HashMap<(String, u16), Vec<(usize, usize)>> = HashMap::new();
+        for (container_index, container) in containers.iter().enumerate() {
+            for (binding_index, binding) in container.ports.iter().enumerate() {
+                if let Some(port) = binding.host_port {
+                    published.entry((binding.protocol.clone(), port)).or_default().push((container_index, binding_index));
+                }
+            }
+        }
+        Ok(ContainerSnapshot { containers, published })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Container> {
+        self.containers.iter().find(|container| container.id == id)
+    }
+
+    /// The container with `ip` on any of its networks
+    pub fn find_by_ip(&self, ip: &str) -> Option<&Container> {
+        self.containers.iter().find(|container| container.ips.iter().any(|address| address == ip))
+    }
+
+    /// The running containers of a Compose project, or of one of its services
+    pub fn compose_containers(&self, project: &str, service: Option<&str>) -> Vec<&Container> {
+        self.containers
+            .iter()
+            .filter(|container| {
+                container.compose().is_some_and(|compose| {
+                    compose.project == project && service.is_none_or(|service| compose.service == service)
+                })
+            })
+            .collect()
+    }
+
+    /// Every container publishing `port`, once per host address it is bound to
+    pub fn published(&self, protocol: &str, port: u16) -> Vec<(&Container, &PortBinding)> {
+        self.published
+            .get(&(protocol.to_string(), port))
+            .into_iter()
+            .flatten()
+            .map(|&(container, binding)| (&self.containers[container], &self.containers[container].ports[binding]))
+            .collect()
+    }
+}
+
+/// Stops the container like `docker stop`: SIGTERM, then SIGKILL after its stop timeout.
+/// A container that has already stopped counts as stopped. A Podman container in a pod
+/// stops with its pod.
+pub(crate) fn stop_container(container: &Container) -> Result<(), EngineError> {
+    if let Engine::Podman { uid } = container.engine {
+        return podman::stop(uid, container);
+    }
+    match request(Engine::Docker, "POST", &format!("/containers/{}/stop", container.id), STOP_TIMEOUT) {
+        Err(EngineError::Api { status: 304, .. }) => Ok(()),
+        result => result.map(|_| ()),
+    }
+}
+
+pub(crate) fn remove_container(container: &Container) -> Result<(), EngineError> {
+    if let Engine::Podman { uid } = container.engine {
+        return podman::remove(uid, container);
+    }
+    request(Engine::Docker, "DELETE", &format!("/containers/{}", container.id), READ_TIMEOUT).map(|_| ())
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ApiContainer {
+    id: String,
+    #[serde(default)]
+    names: Vec<String>,
+    #[serde(default)]
+    image: String,
+    #[serde(default)]
+    ports: Vec<ApiPort>,
+    #[serde(default)]
+    labels: Option<BTreeMap<String, String>>,
+    network_settings: Option<ApiNetworkSettings>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ApiPort {
+    #[serde(rename = "IP")]
+    ip: Option<String>,
+    private_port: u16,
+    public_port: Option<u16>,
+    #[serde(rename = "Type")]
+    protocol: String,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ApiNetworkSettings {
+    #[serde(default)]
+    networks: HashMap<String, ApiNetwork>,
+}
+
+#[derive(serde::Deserialize)]
+struct ApiNetwork {
+    #[serde(rename = "IPAddress", default)]
+    ip_address: String,
+    #[serde(rename = "GlobalIPv6Address", default)]
+    global_ipv6_address: String,
+}
+
+impl From<ApiContainer> for Container {
+    fn from(container: ApiContainer) -> Container {
+        // One address per network, kept apart: a container on two networks has two
+        let ips = container
+            .network_settings
+            .map(|settings| settings.networks.into_values().collect::<Vec<_>>())
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|network| [network.ip_address, network.global_ipv6_address])
+            .filter(|ip| !ip.is_empty())
+            .collect();
+        let ports = container
+            .ports
+            .into_iter()
+            .map(|port| PortBinding {
+                host_ip: port.ip.filter(|ip| !ip.is_empty()),
+                host_port: port.public_port,
+                container_port: port.private_port,
+                protocol: port.protocol,
+            })
+            .collect();
+        Container {
+            engine: Engine::Docker,
+            id: container.id,
+            name: container.names.first().map(|name| name.trim_start_matches('/').to_string()).unwrap_or_default(),
+            image: container.image,
+            ips,
+            ports,
+            labels: container.labels.unwrap_or_default(),
+            pod: None,
+        }
+    }
+}
+
+trait Connection: Read + Write {}
+impl<T: Read + Write> Connection for T {}
+
+/// Sends one request to the engine's API and returns the body of a successful response.
+/// Each request gets its own connection, closed by the daemon once it has answered.
+pub(crate) fn request(engine: Engine, method: &str, path: &str, timeout: Duration) -> Result<Vec<u8>, EngineError> {
+    let host = engine.host();
+    let mut connection = connect(engine, &host, timeout)?;
+
+    let head = format!(
+        "{} {} HTTP/1.1\r\nHost: docker\r\nUser-Agent: apps-ports\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        method, path
+    );
+    let mut response = Vec::new();
+    connection
+        .write_all(head.as_bytes())
+        .and_then(|_| connection.read_to_end(&mut response))
+        .map_err(|e| EngineError::Unavailable { engine, reason: format!("{}: {}", host, e) })?;
+
+    let (status, body) = parse_response(&response)
+        .ok_or_else(|| EngineError::Unavailable { engine, reason: format!("{} did not answer with HTTP", host) })?;
+    if (200..300).contains(&status) {
+        return Ok(body);
+    }
+    Err(api_error(status, &body))
+}
+
+/// Errors come as {"message": "No such container: ..."}, or as plain text from proxies
+fn api_error(status: u16, body: &[u8]) -> EngineError {
+    let message = serde_json::from_slice::<serde_json::Value>(body)
+        .ok()
+        .and_then(|error| error.get("message")?.as_str().map(str::to_string))
+        .unwrap_or_else(|| String::from_utf8_lossy(body).trim().to_string());
+    EngineError::Api { status, message }
+}
+
+/// Connects to `unix://` or plain `tcp://` hosts. TLS, ssh and named pipes need the docker CLI.
+fn connect(engine: Engine, host: &str, timeout: Duration) -> Result<Box<dyn Connection>, EngineError> {
+    let connection_error = |e: io::Error| match e.kind() {
+        io::ErrorKind::PermissionDenied => EngineError::PermissionDenied { engine, host: host.to_string() },
+        _ => EngineError::Unavailable { engine, reason: format!("{}: {}", host, e) },
+    };
+    let unsupported = |reason: &str| EngineError::Unavailable { engine, reason: format!("{}: {}", host, reason) };
+
+    if let Some(path) = host.strip_prefix("unix://") {
+        #[cfg(unix)]
+        {
+            let stream = std::os::unix::net::UnixStream::connect(path).map_err(connection_error)?;
+            stream.set_read_timeout(Some(timeout)).map_err(connection_error)?;
+            return Ok(Box::new(stream));
+        }
+        #[cfg(not(unix))]
+        return Err(EngineError::Unavailable { engine, reason: format!("{}: Unix sockets are not supported here", path) });
+    }
+
+    if let Some(address) = host.strip_prefix("tcp://").or_else(|| host.strip_prefix("http://")) {
+        if env::var("DOCKER_TLS_VERIFY").is_ok_and(|verify| !verify.is_empty()) {
+            return Err(unsupported("TLS connections are not supported"));
+        }
+        let stream = TcpStream::connect(address.trim_end_matches('/')).map_err(connection_error)?;
+        stream.set_read_timeout(Some(timeout)).map_err(connection_error)?;
+        return Ok(Box::new(stream));
+    }
+
+    Err(unsupported("only unix:// and tcp:// hosts are supported"))
+}
+
+/// Splits a raw HTTP/1.1 response into its status code and body, undoing chunked encoding
+fn parse_response(response: &[u8]) -> Option<(u16, Vec<u8>)> {
+    let header_end = response.windows(4).position(|window| window == b"\r\n\r\n")?;
+    let head = String::from_utf8_lossy(&response[..header_end]);
+    let body = &response[header_end + 4..];
+
+    let mut lines = head.lines();
+    let status = lines.next()?.split_whitespace().nth(1)?.parse().ok()?;
+    let chunked = lines.any(|line| {
+        line.split_once(':').is_some_and(|(name, value)| {
+            name.trim().eq_ignore_ascii_case("transfer-encoding") && value.trim().eq_ignore_ascii_case("chunked")
+        })
+    });
+    let body = if chunked { decode_chunked(body)? } else { body.to_vec() };
+    Some((status, body))
+}
+
+/// "1a\r\n<26 bytes>\r\n0\r\n\r\n" -> the bytes
+fn decode_chunked(mut body: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    loop {
+        let line_end = body.windows(2).position(|window| window == b"\r\n")?;
+        let size_line = std::str::from_utf8(&body[..line_end]).ok()?;
+        // Chunk extensions (";name=value") are allowed after the size
+        let size = usize::from_str_radix(size_line.split(';').next()?.trim(), 16).ok()?;
+        body = &body[line_end + 2..];
+        if size == 0 {
+            return Some(decoded);
+        }
+        decoded.extend_from_slice(body.get(..size)?);
+        body = body.get(size + 2..)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_content_length_response() {
+        let response = b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n[]";
+        assert_eq!(parse_response(response), Some((200, b"[]".to_vec())));
+    }
+
+    #[test]
+    fn parses_response_without_body() {
+        assert_eq!(parse_response(b"HTTP/1.1 304 Not Modified\r\n\r\n"), Some((304, Vec::new())));
+    }
+
+    #[test]
+    fn decodes_chunked_response() {
+        let response = b"HTTP/1.1 200 OK\r\ntransfer-encoding: Chunked\r\n\r\n5\r\nhello\r\n7;ext=1\r\n, world\r\n0\r\n\r\n";
+        assert_eq!(parse_response(response), Some((200, b"hello, world".to_vec())));
+    }
+
+    #[test]
+    fn rejects_truncated_chunk() {
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\na\r\nhello";
+        assert_eq!(parse_response(response), None);
+        // The last chunk never came
+        assert_eq!(decode_chunked(b"5\r\nhello\r\n"), None);
+    }
+
+    #[test]
+    fn rejects_malformed_chunk_size() {
+        assert_eq!(decode_chunked(b"zz\r\nhello\r\n0\r\n\r\n"), None);
+        assert_eq!(decode_chunked(b"5hello"), None);
+    }
+
+    #[test]
+    fn rejects_non_http_answer() {
+        assert_eq!(parse_response(b"SSH-2.0-OpenSSH_9.6\r\n"), None);
+        assert_eq!(parse_response(b"garbage\r\n\r\n"), None);
+    }
+
+    #[test]
+    fn reads_message_from_error_body() {
+        let error = api_error(404, br#"{"message":"No such container: web"}"#);
+        assert!(matches!(error, EngineError::Api { status: 404, ref message } if message == "No such container: web"));
+        let error = api_error(502, b"Bad Gateway\n");
+        assert!(matches!(error, EngineError::Api { status: 502, ref message } if message == "Bad Gateway"));
+    }
+
+    /// Answers like the Engine API on a Unix socket, one connection per request, and
+    /// records the request lines it got
+    #[cfg(unix)]
+    fn fake_daemon(path: &Path) -> std::sync::Arc<std::sync::Mutex<Vec<String>>> {
+        use std::os::unix::net::UnixListener;
+        use std::sync::{Arc, Mutex};
+
+        let _ = std::fs::remove_file(path);
+        let listener = UnixListener::bind(path).unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&requests);
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    match stream.read(&mut buffer) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buffer[..n]),
+                    }
+                }
+                let line = String::from_utf8_lossy(&request).lines().next().unwrap_or_default().to_string();
+                let response: Vec<u8> = match line.split_whitespace().take(2).collect::<Vec<_>>()[..] {
+                    ["GET", "/containers/json"] => {
+                        let body = br#"[{"Id":"aaaa","Names":["/web"],"Image":"nginx:latest","Ports":[{"IP":"0.0.0.0","PrivatePort":80,"PublicPort":8080,"Type":"tcp"},{"PrivatePort":443,"Type":"tcp"}],"Labels":{"com.docker.compose.project":"shop","com.docker.compose.service":"web"},"NetworkSettings":{"Networks":{"bridge":{"IPAddress":"172.17.0.2","GlobalIPv6Address":""}}}},{"Id":"bbbb","Names":["/db"],"Image":"postgres:16","Ports":[],"Labels":null,"NetworkSettings":null}]"#;
+                        let (first, second) = body.split_at(body.len() / 2);
+                        let mut response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+                        for part in [first, second] {
+                            response.extend_from_slice(format!("{:x}\r\n", part.len()).as_bytes());
+                            response.extend_from_slice(part);
+                            response.extend_from_slice(b"\r\n");
+                        }
+                        response.extend_from_slice(b"0\r\n\r\n");
+                        response
+                    }
+                    ["POST", "/containers/aaaa/stop"] | ["DELETE", "/containers/aaaa"] => {
+                        b"HTTP/1.1 204 No Content\r\n\r\n".to_vec()
+                    }
+                    ["POST", "/containers/bbbb/stop"] => b"HTTP/1.1 304 Not Modified\r\n\r\n".to_vec(),
+                    _ => {
+                        let body = br#"{"message":"No such container: cccc"}"#;
+                        let mut response = format!("HTTP/1.1 404 Not Found\r\nContent-Length: {}\r\n\r\n", body.len()).into_bytes();
+                        response.extend_from_slice(body);
+                        response
+                    }
+                };
+                log.lock().unwrap().push(line);
+                let _ = stream.write_all(&response);
+            }
+        });
+        requests
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn lists_stops_and_removes_through_fake_daemon() {
+        let path = env::temp_dir().join(format!("apps-ports-test-{}.sock", std::process::id()));
+        let requests = fake_daemon(&path);
+        // The only test that talks to a daemon, so nothing else reads DOCKER_HOST meanwhile
+        env::set_var("DOCKER_HOST", format!("unix://{}", path.display()));
+
+        let snapshot = ContainerSnapshot::load(Engine::Docker, false).unwrap_or_else(|e| panic!("{}", e));
+        let web = snapshot.get("aaaa").unwrap();
+        assert_eq!(web.name, "web");
+        assert_eq!(web.ips, vec!["172.17.0.2".to_string()]);
+        assert_eq!(web.compose().map(|compose| compose.service), Some("web".to_string()));
+        assert_eq!(snapshot.published("tcp", 8080).len(), 1);
+        assert!(snapshot.published("tcp", 443).is_empty());
+        let db = snapshot.get("bbbb").unwrap();
+        assert!(db.labels.is_empty() && db.ports.is_empty());
+
+        assert!(stop_container(web).is_ok());
+        // Already stopped
+        assert!(stop_container(db).is_ok());
+        assert!(remove_container(web).is_ok());
+        let missing = Container { id: "cccc".to_string(), ..web.clone() };
+        match stop_container(&missing) {
+            Err(EngineError::Api { status: 404, message }) => assert_eq!(message, "No such container: cccc"),
+            _ => panic!("expected a 404 from the daemon"),
+        }
+
+        assert_eq!(
+            *requests.lock().unwrap(),
+            [
+                "GET /containers/json HTTP/1.1",
+                "POST /containers/aaaa/stop HTTP/1.1",
+                "POST /containers/bbbb/stop HTTP/1.1",
+                "DELETE /containers/aaaa HTTP/1.1",
+                "POST /containers/cccc/stop HTTP/1.1",
+            ]
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -1,18 +1,454 @@
-use clap::{Arg, Command, ArgAction};
+mod connections;
+mod docker;
+mod managers;
+#[cfg(target_os = "linux")]
+mod netlink;
+#[cfg(target_os = "linux")]
+mod netns;
+mod podman;
+mod privileged;
+mod process_table;
+mod process_tree;
+mod procfs;
+mod protected;
+mod release;
+mod signal;
+mod sources;
+mod systemd;
+
+use clap::{Arg, Command, ArgAction, ValueEnum};
 use std::process::{Command as StdCommand, Stdio};
-use std::io::{self, Write};
-use tabled::{Table, Tabled, settings::{Width, Settings, Modify, object::Columns}};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use process_table::{ProcessTable, StartTime};
+use process_tree::ProcessTree;
+use protected::ProtectedList;
+use signal::{Signal, SignalError, Target, SIGKILL};
+use docker::{ComposeService, Container, ContainerSnapshot, Engine, PortBinding, Snapshots};
+use managers::{ManagedApp, StopMethod};
+use systemd::{StopError, SystemdUnit};
+use tabled::{Table, Tabled, settings::{Disable, Width, Settings, Modify, location::ByColumnName, object::Columns}};
 
-#[derive(Tabled, serde::Serialize)]
+#[derive(Clone, Tabled, serde::Serialize)]
 struct ProcessInfo {
     port: String,
+    #[tabled(rename = "proto")]
+    protocol: Protocol,
+    address: String,
+    family: AddressFamily,
+    #[tabled(display_with = "display_clients")]
+    clients: Option<usize>,
     pid: String,
+    /// Every process holding the socket open, main PID first
+    #[tabled(rename = "workers", display_with = "display_workers")]
+    holders: Vec<Holder>,
     process_name: String,
     command: String,
     #[tabled(rename = "docker_id")]
     docker_container_id: String,
     #[tabled(rename = "docker_image")]
     docker_image: String,
+    /// The container's labels, only in the JSON output
+    #[tabled(skip)]
+    docker_labels: BTreeMap<String, String>,
+    /// Docker or Podman, for rows of a port forwarder or published port a container was found for
+    #[tabled(skip)]
+    container_engine: Option<Engine>,
+    /// The Podman pod of the container, only in the JSON output
+    #[tabled(skip)]
+    pod: String,
+    /// The Compose project and service of the container, and the directory it was started from
+    compose_project: String,
+    compose_service: String,
+    compose_dir: String,
+    /// The systemd service running the process, or the .socket unit of a socket-activated listener
+    #[tabled(display_with = "display_unit")]
+    unit: Option<SystemdUnit>,
+    /// The process manager (pm2, supervisord, foreman, overmind, honcho) running the process
+    #[tabled(display_with = "display_managed_by")]
+    managed_by: Option<ManagedApp>,
+    /// Network namespace the socket lives in, filled in with --all-netns
+    netns: String,
+    #[tabled(skip)]
+    uid: Option<u32>,
+    #[tabled(skip)]
+    inode: Option<u64>,
+    #[tabled(skip)]
+    recv_q: Option<u32>,
+    #[tabled(skip)]
+    send_q: Option<u32>,
+    /// Whether an IPv6 listener has IPV6_V6ONLY set, where the source reports it
+    #[tabled(skip)]
+    #[serde(skip)]
+    v6only: Option<bool>,
+    /// When the main process started, in seconds since the Unix epoch
+    #[tabled(skip)]
+    start_time: Option<u64>,
+    /// Which discovery sources reported the socket, shown by --explain-sources
+    #[tabled(skip)]
+    sources: Vec<Sighting>,
+}
+
+impl ProcessInfo {
+    /// The socket was found but none of the sources could see which process holds it
+    fn is_hidden(&self) -> bool {
+        self.pid == "hidden"
+    }
+
+    /// A port a container publishes with no host process to signal: none listens on it, or
+    /// the one that does (docker-proxy, as root) is hidden from this user
+    fn is_container_only(&self) -> bool {
+        self.holders.is_empty() && !self.docker_container_id.is_empty()
+    }
+
+    /// An IPv6 socket also takes IPv4 connections (as IPv4-mapped addresses) unless it
+    /// sets IPV6_V6ONLY, which is off by default on Linux
+    fn is_dual_stack(&self) -> bool {
+        self.family == AddressFamily::Ipv6 && self.v6only != Some(true)
+    }
+}
+
+/// One source reporting a socket, with the PID it attributed it to ("hidden" if none)
+#[derive(Clone, serde::Serialize)]
+struct Sighting {
+    source: &'static str,
+    pid: String,
+    /// Found by asking about this one port, after the scans left the socket hidden
+    lookup: bool,
+}
+
+/// A process with a file descriptor for a socket. Pre-fork servers (nginx, gunicorn,
+/// php-fpm) share one listening socket between a master and its forked workers.
+#[derive(Clone, serde::Serialize)]
+struct Holder {
+    pid: String,
+    process_name: String,
+    role: HolderRole,
+    /// Seconds since the Unix epoch
+    start_time: Option<u64>,
+    /// To tell the process apart from a later one reusing its PID
+    #[serde(skip)]
+    started: Option<StartTime>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+enum HolderRole {
+    /// Opened the socket, or at least didn't inherit it from another holder
+    Master,
+    /// Inherited the socket from a holder parent
+    Worker,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Protocol {
+    Tcp,
+    Udp,
+    Unix,
+}
+
+impl Protocol {
+    /// Parses protocol names as printed by ss, netstat and lsof (tcp, tcp6, UDP, ...)
+    fn from_name(name: &str) -> Option<Protocol> {
+        let name = name.to_lowercase();
+        if name.starts_with("tcp") {
+            Some(Protocol::Tcp)
+        } else if name.starts_with("udp") {
+            Some(Protocol::Udp)
+        } else {
+            None
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Protocol::Tcp => "tcp",
+            Protocol::Udp => "udp",
+            Protocol::Unix => "unix",
+        }
+    }
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+enum AddressFamily {
+    Ipv4,
+    Ipv6,
+    Unix,
+}
+
+impl AddressFamily {
+    /// Guesses the family from a bare host as printed by the socket tools
+    fn from_host(host: &str) -> AddressFamily {
+        // ss prints the IPv6 wildcard of a dual-stack socket as "*"
+        if host.contains(':') || host == "*" {
+            AddressFamily::Ipv6
+        } else {
+            AddressFamily::Ipv4
+        }
+    }
+
+    fn wildcard(&self) -> &'static str {
+        match self {
+            AddressFamily::Ipv4 => "0.0.0.0",
+            AddressFamily::Ipv6 => "::",
+            AddressFamily::Unix => "",
+        }
+    }
+}
+
+impl fmt::Display for AddressFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressFamily::Ipv4 => f.write_str("IPv4"),
+            AddressFamily::Ipv6 => f.write_str("IPv6"),
+            AddressFamily::Unix => f.write_str("Unix"),
+        }
+    }
+}
+
+/// Splits "host:port" as printed by ss, netstat and lsof
+/// ("0.0.0.0:80", "[::1]:631", ":::22", "*:5353", "127.0.0.53%lo:53")
+fn split_host_port(address: &str) -> Option<(&str, &str)> {
+    let (host, port) = address.rsplit_once(':')?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    // Drop the interface scope ss appends to some addresses
+    let host = host.split('%').next().unwrap_or(host);
+    Some((host, port))
+}
+
+/// Spells wildcard addresses the same way for every backend
+fn normalize_host(host: &str, family: AddressFamily) -> String {
+    if host == "*" || host.is_empty() {
+        family.wildcard().to_string()
+    } else {
+        host.to_string()
+    }
+}
+
+fn format_socket_address(address: &str, port: &str) -> String {
+    // Unix sockets have a path (or abstract name) but no port
+    if port.is_empty() {
+        address.to_string()
+    } else if address.contains(':') {
+        format!("[{}]:{}", address, port)
+    } else {
+        format!("{}:{}", address, port)
+    }
+}
+
+/// Formats a row's socket as "127.0.0.1:3000/tcp", or just the path for Unix sockets
+fn format_endpoint(process: &ProcessInfo) -> String {
+    if process.protocol == Protocol::Unix {
+        process.address.clone()
+    } else {
+        format!("{}/{}", format_socket_address(&process.address, &process.port), process.protocol)
+    }
+}
+
+fn same_socket_path(a: &str, b: &str) -> bool {
+    if a == b {
+        return true;
+    }
+    // Sockets may be bound through symlinked directories such as /var/run -> /run
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn same_address(a: &str, b: &str) -> bool {
+    match (a.parse::<IpAddr>(), b.parse::<IpAddr>()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Whether an address given to -p/-k and a socket's address can be the same endpoint. A
+/// wildcard on either side covers every address of its family, IPv4-mapped ones for [::],
+/// and a `dual_stack` [::] listener IPv4 addresses as well; two specific addresses have to
+/// be the same.
+fn addresses_overlap(spec: &str, address: &str, dual_stack: bool) -> bool {
+    match (spec.parse::<IpAddr>(), address.parse::<IpAddr>()) {
+        (Ok(spec), Ok(address)) if spec.is_unspecified() || address.is_unspecified() => {
+            spec.is_ipv4() == address.is_ipv4() || (dual_stack && address.is_unspecified() && spec.is_ipv4())
+        }
+        _ => same_address(spec, address),
+    }
+}
+
+/// A port given to -p/-k, optionally restricted to one address and protocol
+/// (e.g. `53/udp`, `127.0.0.1:5432` or `[::1]:5432/tcp`), or a Unix socket path
+/// (`/run/docker.sock`, `@abstract-name`)
+#[derive(Clone)]
+struct PortSpec {
+    address: Option<String>,
+    port: String,
+    protocol: Option<Protocol>,
+}
+
+impl PortSpec {
+    fn parse(spec: &str) -> Result<PortSpec, String> {
+        if spec.starts_with('/') || spec.starts_with('@') {
+            return Ok(PortSpec {
+                address: Some(spec.to_string()),
+                port: String::new(),
+                protocol: Some(Protocol::Unix),
+            });
+        }
+
+        let (endpoint, protocol) = match spec.split_once('/') {
+            Some((endpoint, name)) => {
+                let protocol = Protocol::from_name(name)
+                    .filter(|protocol| protocol.as_str() == name.to_lowercase())
+                    .ok_or_else(|| format!("unknown protocol '{}', expected tcp or udp", name))?;
+                (endpoint, Some(protocol))
+            }
+            None => (spec, None),
+        };
+
+        let (address, port) = match split_host_port(endpoint) {
+            // ":80" is port 80 on any address
+            Some(("", port)) => (None, port),
+            Some((host, port)) => {
+                if host.parse::<IpAddr>().is_err() {
+                    return Err(format!("invalid address '{}'", host));
+                }
+                (Some(host.to_string()), port)
+            }
+            None => (None, endpoint),
+        };
+
+        let Ok(port) = port.parse::<u16>() else {
+            return Err(format!("invalid port '{}'", port));
+        };
+
+        // Rows hold the port as the kernel prints it, so "03000" has to become "3000"
+        Ok(PortSpec { address, port: port.to_string(), protocol })
+    }
+
+    fn is_unix(&self) -> bool {
+        self.protocol == Some(Protocol::Unix)
+    }
+
+    fn unix_path(&self) -> Option<&str> {
+        self.address.as_deref().filter(|_| self.is_unix())
+    }
+
+    /// Describes the target for messages, e.g. "port 53/udp" or "socket /run/app.sock"
+    fn describe(&self) -> String {
+        if self.is_unix() {
+            format!("socket {}", self)
+        } else {
+            format!("port {}", self)
+        }
+    }
+
+    fn matches_socket(&self, address: IpAddr, port: u16) -> bool {
+        self.port == port.to_string()
+            && self.address.as_deref().is_none_or(|spec| addresses_overlap(spec, &address.to_canonical().to_string(), false))
+    }
+
+    fn matches(&self, process: &ProcessInfo) -> bool {
+        if self.is_unix() {
+            return process.protocol == Protocol::Unix
+                && self.address.as_deref().is_some_and(|path| same_socket_path(path, &process.address));
+        }
+
+        process.port == self.port
+            && self.protocol.is_none_or(|protocol| process.protocol == protocol)
+            && self.address.as_deref().is_none_or(|address| addresses_overlap(address, &process.address, process.is_dual_stack()))
+    }
+}
+
+impl fmt::Display for PortSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(path) = self.unix_path() {
+            return f.write_str(path);
+        }
+
+        match &self.address {
+            Some(address) => f.write_str(&format_socket_address(address, &self.port))?,
+            None => f.write_str(&self.port)?,
+        }
+        if let Some(protocol) = self.protocol {
+            write!(f, "/{}", protocol)?;
+        }
+        Ok(())
+    }
+}
+
+/// Label for rows from our own network namespace when scanning all of them
+const HOST_NAMESPACE: &str = "host";
+
+/// How listeners are discovered, shared by the listing, -p and -k
+#[derive(Clone, Copy)]
+struct ScanOptions {
+    backend: Backend,
+    all_netns: bool,
+    /// Look up the owners of hidden rows with sudo
+    sudo: bool,
+}
+
+/// How -k stops the processes it finds
+struct KillOptions {
+    signal: Signal,
+    /// How long to wait for the process to exit before escalating to SIGKILL
+    grace: Duration,
+    escalate: bool,
+    scope: KillScope,
+    /// Answer every prompt with yes, for scripts
+    yes: bool,
+    /// Only print what would be done
+    dry_run: bool,
+    /// Kill protected processes too
+    force: bool,
+}
+
+impl KillOptions {
+    fn escalates(&self) -> bool {
+        self.escalate && self.signal != SIGKILL
+    }
+}
+
+/// Which processes -k takes down along with the listener, selected with --scope
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum KillScope {
+    /// Only the process holding the port
+    Process,
+    /// The listener's whole process group
+    Group,
+    /// The listener and all its descendants
+    Tree,
+    /// The wrapper that started the listener (npm, sh -c, cargo watch...) and everything under it
+    Launcher,
+}
+
+/// Socket discovery backend selected with --backend
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Backend {
+    /// Netlink sock_diag, then /proc, then ss/netstat/lsof
+    Auto,
+    /// NETLINK_SOCK_DIAG (Linux only)
+    Netlink,
+    /// /proc/net/tcp, tcp6, udp and udp6
+    Proc,
+    /// The ss command
+    Ss,
+    /// The netstat command
+    Netstat,
+    /// The lsof command
+    Lsof,
 }
 
 fn main() {
@@ -24,7 +460,8 @@ fn main() {
                 .short('p')
                 .long("port")
                 .value_name("PORT")
-                .help("Specific port to check")
+                .value_parser(PortSpec::parse)
+                .help("Specific port to check (e.g. 3000, 53/udp, 127.0.0.1:5432 or a Unix socket path)")
         )
         .arg(
             Arg::new("list")
@@ -38,13 +475,81 @@ fn main() {
                 .short('k')
                 .long("kill")
                 .value_name("PORT")
-                .help("Kill process using the specified port")
+                .value_parser(PortSpec::parse)
+                .help("Kill process using the specified port (e.g. 3000, 53/udp, 127.0.0.1:5432 or a Unix socket path)")
+        )
+        .arg(
+            Arg::new("unix")
+                .short('x')
+                .long("unix")
+                .action(ArgAction::SetTrue)
+                .help("List listening Unix domain sockets instead of ports")
+        )
+        .arg(
+            Arg::new("connections")
+                .long("connections")
+                .action(ArgAction::SetTrue)
+                .requires("port")
+                .help("With -p, list established connections to and from the port")
         )
         .arg(
             Arg::new("kill_docker_container")
                 .long("kill-docker-container")
                 .action(ArgAction::SetTrue)
-                .help("When used with -k, kill Docker container instead of just the process")
+                .help("When used with -k, stop the Docker or Podman container (or Podman pod) instead of just the process")
+        )
+        .arg(
+            Arg::new("signal")
+                .long("signal")
+                .value_name("SIGNAL")
+                .value_parser(Signal::parse)
+                .default_value("TERM")
+                .help("Signal to send with -k: TERM, INT, HUP, QUIT, KILL or a number")
+        )
+        .arg(
+            Arg::new("grace")
+                .long("grace")
+                .value_name("SECONDS")
+                .value_parser(parse_grace)
+                .default_value("5")
+                .help("With -k, how long to wait for the process to exit before sending SIGKILL")
+        )
+        .arg(
+            Arg::new("no_escalate")
+                .long("no-escalate")
+                .action(ArgAction::SetTrue)
+                .help("With -k, only send the signal and don't follow up with SIGKILL")
+        )
+        .arg(
+            Arg::new("scope")
+                .long("scope")
+                .value_name("SCOPE")
+                .value_parser(clap::value_parser!(KillScope))
+                .default_value("process")
+                .help("With -k, what to kill along with the listener")
+        )
+        .arg(
+            Arg::new("yes")
+                .short('y')
+                .long("yes")
+                .action(ArgAction::SetTrue)
+                .requires("kill")
+                .help("With -k, kill without asking for confirmation")
+        )
+        .arg(
+            Arg::new("dry_run")
+                .long("dry-run")
+                .action(ArgAction::SetTrue)
+                .requires("kill")
+                .conflicts_with("yes")
+                .help("With -k, print what would be killed and how, without doing it")
+        )
+        .arg(
+            Arg::new("force")
+                .long("force")
+                .action(ArgAction::SetTrue)
+                .requires("kill")
+                .help("With -k, also kill protected processes (PID 1, sshd, dockerd, your shell...)")
         )
         .arg(
             Arg::new("compact")
@@ -67,184 +572,429 @@ fn main() {
                 .action(ArgAction::SetTrue)
                 .help("Output in JSON format")
         )
+        .arg(
+            Arg::new("backend")
+                .long("backend")
+                .value_name("BACKEND")
+                .value_parser(clap::value_parser!(Backend))
+                .default_value("auto")
+                .help("Socket discovery backend")
+        )
+        .arg(
+            Arg::new("all_netns")
+                .short('N')
+                .long("all-netns")
+                .action(ArgAction::SetTrue)
+                .help("Also scan other network namespaces (containers, ip netns, PrivateNetwork= services)")
+        )
+        .arg(
+            Arg::new("sudo")
+                .long("sudo")
+                .action(ArgAction::SetTrue)
+                .help("Look up the owners of hidden sockets with sudo (tries sudo -n, then asks for a password)")
+        )
+        .arg(
+            // Internal: the privileged half of --sudo
+            Arg::new("socket_owners")
+                .long("socket-owners")
+                .action(ArgAction::SetTrue)
+                .hide(true)
+        )
+        .arg(
+            Arg::new("explain_sources")
+                .long("explain-sources")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("kill")
+                .help("Run every discovery source and show what each one reported, optionally for -p only")
+        )
         .get_matches();
 
     let compact = matches.get_flag("compact");
     let simple = matches.get_flag("simple");
     let json_output = matches.get_flag("json");
+    let options = ScanOptions {
+        backend: *matches.get_one::<Backend>("backend").unwrap(),
+        all_netns: matches.get_flag("all_netns"),
+        sudo: matches.get_flag("sudo"),
+    };
+    let unix = matches.get_flag("unix");
     
-    if let Some(port) = matches.get_one::<String>("kill") {
+    if matches.get_flag("socket_owners") {
+        privileged::print_socket_owners();
+    } else if matches.get_flag("explain_sources") {
+        sources::explain_sources(matches.get_one::<PortSpec>("port"), json_output);
+    } else if let Some(port) = matches.get_one::<PortSpec>("kill") {
         let kill_docker = matches.get_flag("kill_docker_container");
-        kill_process_by_port(port, kill_docker);
-    } else if let Some(port) = matches.get_one::<String>("port") {
-        show_process_by_port(port, compact, simple, json_output);
-    } else if matches.get_flag("list") {
-        list_all_processes(compact, simple, json_output);
+        let kill_options = KillOptions {
+            signal: *matches.get_one::<Signal>("signal").unwrap(),
+            grace: *matches.get_one::<Duration>("grace").unwrap(),
+            escalate: !matches.get_flag("no_escalate"),
+            scope: *matches.get_one::<KillScope>("scope").unwrap(),
+            yes: matches.get_flag("yes"),
+            dry_run: matches.get_flag("dry_run"),
+            force: matches.get_flag("force"),
+        };
+        if !kill_process_by_port(port, kill_docker, &options, &kill_options) {
+            std::process::exit(1);
+        }
+    } else if let Some(port) = matches.get_one::<PortSpec>("port") {
+        if matches.get_flag("connections") {
+            connections::list_connections(port, options.sudo, simple, json_output);
+        } else {
+            show_process_by_port(port, &options, compact, simple, json_output);
+        }
     } else {
-        list_all_processes(compact, simple, json_output);
+        // Listing is the default action, with or without --list
+        list_all_processes(&options, unix, compact, simple, json_output);
     }
 }
 
-fn get_processes_using_ports() -> Vec<ProcessInfo> {
-    let mut processes = Vec::new();
+fn parse_grace(value: &str) -> Result<Duration, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| format!("invalid number of seconds '{}'", value))
+}
+
+fn get_processes_using_ports(options: &ScanOptions) -> Vec<ProcessInfo> {
+    let mut processes = sources::discover_listeners(options.backend);
+    connections::count_clients(&mut processes);
 
-    // Try ss first (modern replacement for netstat)
-    if let Some(ss_processes) = try_ss_command() {
-        processes.extend(ss_processes);
+    if options.all_netns {
+        for process in &mut processes {
+            process.netns = HOST_NAMESPACE.to_string();
+        }
+        processes.extend(scan_other_namespaces());
     }
 
-    // Try netstat as fallback
-    let output = StdCommand::new("netstat")
-        .args(["-tlnp"])
-        .stdout(Stdio::piped())
-        .stderr(Stdio::null())
-        .output();
+    processes
+}
 
-    if let Ok(output) = output {
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        for line in stdout.lines() {
-            if line.contains("LISTEN") {
-                if let Some(process_info) = parse_netstat_line(line) {
-                    // Check if we already have this process to avoid duplicates
-                    if !processes.iter().any(|p| p.pid == process_info.pid && p.port == process_info.port) {
-                        processes.push(process_info);
-                    }
-                }
-            }
+#[cfg(target_os = "linux")]
+fn scan_other_namespaces() -> Vec<ProcessInfo> {
+    netns::scan_other_namespaces()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn scan_other_namespaces() -> Vec<ProcessInfo> {
+    Vec::new()
+}
+
+fn get_process_name_by_pid(pid: &str) -> String {
+    if let Ok(name) = std::fs::read_to_string(format!("/proc/{}/comm", pid)) {
+        return name.trim().to_string();
+    }
+
+    if let Ok(output) = StdCommand::new("ps")
+        .args(["-p", pid, "-o", "comm", "--no-headers"])
+        .output()
+    {
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    } else {
+        "unknown".to_string()
+    }
+}
+
+/// Builds a row for a socket held by one or more processes. Process details and which
+/// holders are workers are filled in by `enrich_processes` once rows are merged.
+fn create_shared_process_info(
+    protocol: Protocol,
+    family: AddressFamily,
+    address: String,
+    port: String,
+    pids: &[u32],
+) -> ProcessInfo {
+    let Some(first) = pids.first() else {
+        return hidden_process_info(protocol, family, address, port);
+    };
+
+    let mut process_info =
+        create_process_info(protocol, family, address, port, first.to_string(), String::new(), String::new());
+    process_info.holders = pids
+        .iter()
+        .map(|pid| Holder {
+            pid: pid.to_string(),
+            process_name: String::new(),
+            role: HolderRole::Master,
+            start_time: None,
+            started: None,
+        })
+        .collect();
+    process_info
+}
+
+/// Finishes rows that are about to be shown: looks up hidden owners by port (only in auto
+/// mode, a forced backend reports what it alone can see) and with sudo when asked to, then
+/// looks up process details
+fn resolve_and_enrich(processes: &mut [ProcessInfo], options: &ScanOptions, snapshots: &mut Snapshots) {
+    if options.backend == Backend::Auto {
+        sources::look_up_hidden_owners(processes);
+    }
+    if options.sudo {
+        privileged::resolve_hidden_with_sudo(processes);
+        for process in processes.iter_mut().filter(|process| process.is_hidden()) {
+            process.command = hidden_command(true).to_string();
         }
     }
+    enrich_processes(processes, snapshots);
+}
 
-    // Try lsof as additional fallback
-    let output = StdCommand::new("lsof")
-        .args(["-i", "-P", "-n", "-sTCP:LISTEN"])
-        .stdout(Stdio::piped())
-        .stderr(Stdio::null())
-        .output();
+/// Fills in names, commands, start times, holder roles and Docker details, looking up
+/// every PID in one pass. Runs only on rows that survived merging and filtering.
+fn enrich_processes(processes: &mut [ProcessInfo], snapshots: &mut Snapshots) {
+    let pids: Vec<u32> = processes
+        .iter()
+        .flat_map(|process| &process.holders)
+        .filter_map(|holder| holder.pid.parse().ok())
+        .collect();
+    let mut table = ProcessTable::load(&pids);
 
-    if let Ok(output) = output {
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        for line in stdout.lines().skip(1) { // Skip header
-            if let Some(process_info) = parse_lsof_line(line) {
-                // Check if we already have this process to avoid duplicates
-                if !processes.iter().any(|p| p.pid == process_info.pid && p.port == process_info.port) {
-                    processes.push(process_info);
-                }
-            }
+    for process_info in processes.iter_mut() {
+        enrich_process(process_info, &table);
+    }
+    systemd::attach_socket_units(processes);
+    managers::find_managed_apps(processes, &mut table);
+    attach_containers(processes, snapshots);
+}
+
+fn enrich_process(process_info: &mut ProcessInfo, table: &ProcessTable) {
+    let pids: Vec<u32> = process_info.holders.iter().filter_map(|holder| holder.pid.parse().ok()).collect();
+    for holder in &mut process_info.holders {
+        let meta = holder.pid.parse().ok().and_then(|pid| table.get(pid));
+        // Holders that inherited the socket from a holder parent are workers
+        let inherited = meta.and_then(|meta| meta.ppid).is_some_and(|parent| pids.contains(&parent));
+        holder.role = if inherited { HolderRole::Worker } else { HolderRole::Master };
+        if let Some(meta) = meta {
+            holder.process_name = meta.comm.clone();
+            holder.start_time = meta.start_time;
+            holder.started = meta.started;
         }
     }
 
-    processes
+    // Masters first, keeping the discovery order otherwise; the topmost holder is the row's PID
+    process_info.holders.sort_by_key(|holder| holder.role == HolderRole::Worker);
+    let Some(main) = process_info.holders.first() else {
+        return;
+    };
+
+    process_info.pid = main.pid.clone();
+    let meta = main.pid.parse().ok().and_then(|pid| table.get(pid));
+    match meta {
+        Some(meta) => {
+            process_info.process_name = meta.comm.clone();
+            process_info.command = meta.cmdline.clone();
+            process_info.start_time = meta.start_time;
+            process_info.unit = main.pid.parse().ok().and_then(systemd::unit_for_pid);
+            // Sources that don't report the socket's owner get the process's uid instead
+            process_info.uid = process_info.uid.or(meta.uid);
+        }
+        // The process exited between the scan and the lookup
+        None if process_info.process_name.is_empty() => process_info.process_name = "unknown".to_string(),
+        None => {}
+    }
 }
 
-fn parse_netstat_line(line: &str) -> Option<ProcessInfo> {
-    let parts: Vec<&str> = line.split_whitespace().collect();
-    if parts.len() >= 7 {
-        let address = parts[3];
-        if let Some(port) = address.split(':').last() {
-            let pid_info = parts[6];
-            if pid_info != "-" {
-                let pid_parts: Vec<&str> = pid_info.split('/').collect();
-                if pid_parts.len() >= 2 {
-                    let pid = pid_parts[0].to_string();
-                    let process_name = pid_parts[1].to_string();
-                    let command = get_command_by_pid(&pid);
-                    return Some(create_process_info(
-                        port.to_string(),
-                        pid,
-                        process_name,
-                        command,
-                    ));
+/// Names the containers behind port forwarder rows (docker-proxy, or Podman's conmon,
+/// rootlessport, pasta and slirp4netns), all resolved against one container snapshot per
+/// engine
+fn attach_containers(processes: &mut [ProcessInfo], snapshots: &mut Snapshots) {
+    for process in processes.iter_mut() {
+        // docker-proxy runs as root, so to a docker group member its sockets are hidden rows;
+        // the container publishing the port still says what they are. Ports are only
+        // published in our own namespace: a hidden socket in another one merely shares the number.
+        if process.is_hidden() && process.protocol != Protocol::Unix {
+            if in_host_namespace(process) {
+                if let Some(container) = snapshots.all().into_iter().find_map(|snapshot| published_container(snapshot, process)) {
+                    set_container(process, container);
                 }
             }
+            continue;
+        }
+        let Some(engine) = forwarding_engine(process) else {
+            continue;
+        };
+        // conmon names its container on the command line, even when Podman can't be asked.
+        // The other forwarders only count as the engine's once a container is found: pasta
+        // and slirp4netns also serve VMs and rootless Docker.
+        if process.process_name == "conmon" {
+            if let Some(id) = podman::conmon_container_id(&process.command) {
+                process.docker_container_id = id.to_string();
+                process.container_engine = Some(engine);
+            }
+        }
+        let Ok(snapshot) = snapshots.get(engine) else {
+            continue;
+        };
+        if let Some(container) = container_for_forwarder(snapshot, process) {
+            set_container(process, container);
         }
     }
-    None
 }
 
-fn parse_lsof_line(line: &str) -> Option<ProcessInfo> {
-    let parts: Vec<&str> = line.split_whitespace().collect();
-    if parts.len() >= 9 {
-        let process_name = parts[0].to_string();
-        let pid = parts[1].to_string();
-        let address = parts[8];
-        
-        if let Some(port_part) = address.split(':').last() {
-            if let Some(port) = port_part.split('(').next() {
-                let command = get_command_by_pid(&pid);
-                return Some(create_process_info(
-                    port.to_string(),
-                    pid,
-                    process_name,
-                    command,
-                ));
-            }
-        }
+/// The engine whose port forwarder holds the row's socket. Podman's run as the user whose
+/// containers they serve, root for rootful ones.
+fn forwarding_engine(process: &ProcessInfo) -> Option<Engine> {
+    if process.command.contains("docker-proxy") {
+        return Some(Engine::Docker);
     }
-    None
+    let uid = process.uid.unwrap_or_else(privileged::effective_uid);
+    podman::is_port_forwarder(&process.process_name).then_some(Engine::Podman { uid })
 }
 
-fn get_command_by_pid(pid: &str) -> String {
-    if let Ok(output) = StdCommand::new("ps")
-        .args(["-p", pid, "-o", "cmd", "--no-headers"])
-        .output()
-    {
-        String::from_utf8_lossy(&output.stdout).trim().to_string()
-    } else {
-        "Unknown".to_string()
+fn set_container(process: &mut ProcessInfo, container: &Container) {
+    process.container_engine = Some(container.engine);
+    process.pod = container.pod.clone().unwrap_or_default();
+    process.docker_container_id = container.id.clone();
+    process.docker_image = container.image.clone();
+    process.docker_labels = container.labels.clone();
+    if let Some(compose) = container.compose() {
+        process.compose_project = compose.project;
+        process.compose_service = compose.service;
+        process.compose_dir = compose.working_dir.unwrap_or_default();
     }
 }
 
-fn get_process_name_by_pid(pid: &str) -> String {
-    if let Ok(output) = StdCommand::new("ps")
-        .args(["-p", pid, "-o", "comm", "--no-headers"])
-        .output()
-    {
-        String::from_utf8_lossy(&output.stdout).trim().to_string()
-    } else {
-        "unknown".to_string()
+/// The container a forwarder row stands for: the one conmon monitors, or the one publishing
+/// the row's port on the row's address, or failing that the one with docker-proxy's
+/// `-container-ip` on any network
+fn container_for_forwarder<'a>(snapshot: &'a ContainerSnapshot, process: &ProcessInfo) -> Option<&'a Container> {
+    if process.process_name == "conmon" {
+        return snapshot.get(podman::conmon_container_id(&process.command)?);
     }
+    published_container(snapshot, process).or_else(|| {
+        // /usr/bin/docker-proxy -proto tcp -host-ip 0.0.0.0 -host-port 8080 -container-ip 172.17.0.2 -container-port 8080
+        let container_ip = process.command.split_whitespace().skip_while(|arg| *arg != "-container-ip").nth(1)?;
+        snapshot.find_by_ip(container_ip)
+    })
 }
 
-fn get_docker_info_from_command(command: &str) -> (String, String) {
-    // Check if this is a docker-proxy process
-    if command.contains("docker-proxy") {
-        if let Some(container_id) = extract_container_id_from_docker_proxy(command) {
-            let image_name = get_container_image(&container_id);
-            return (container_id, image_name);
-        }
+/// The container publishing the row's port on the row's address
+fn published_container<'a>(snapshot: &'a ContainerSnapshot, process: &ProcessInfo) -> Option<&'a Container> {
+    let port = process.port.parse().ok()?;
+    snapshot
+        .published(process.protocol.as_str(), port)
+        .into_iter()
+        .find(|(_, binding)| binding_on_address(binding, &process.address))
+        .map(|(container, _)| container)
+}
+
+/// Whether a port binding is bound to `address`. A binding without a HostIp is on every
+/// address, which only a wildcard listener can stand for.
+fn binding_on_address(binding: &PortBinding, address: &str) -> bool {
+    match binding.host_ip.as_deref() {
+        Some(ip) => same_address(ip, address),
+        None => address.parse::<IpAddr>().is_ok_and(|address| address.is_unspecified()),
     }
-    ("".to_string(), "".to_string())
 }
 
-fn get_container_image(container_id: &str) -> String {
-    let output = StdCommand::new("docker")
-        .args(["inspect", "-f", "{{.Config.Image}}", container_id])
-        .output();
-        
-    if let Ok(output) = output {
-        let image = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        if !image.is_empty() {
-            return image;
+/// Rows from our own network namespace, which is the only one there is without --all-netns
+fn in_host_namespace(process: &ProcessInfo) -> bool {
+    process.netns.is_empty() || process.netns == HOST_NAMESPACE
+}
+
+/// Rows for ports a container publishes with no host process listening on them, as with
+/// `userland-proxy: false`, where Docker forwards them with iptables alone. Bindings that
+/// a forwarder row already stands for are left out.
+fn published_port_rows(port: &PortSpec, found: &[ProcessInfo], snapshots: &[&ContainerSnapshot]) -> Vec<ProcessInfo> {
+    let Ok(number) = port.port.parse::<u16>() else {
+        return Vec::new();
+    };
+
+    let mut rows = Vec::new();
+    for (snapshot, protocol) in snapshots.iter().flat_map(|&snapshot| [(snapshot, Protocol::Tcp), (snapshot, Protocol::Udp)]) {
+        for (container, binding) in snapshot.published(protocol.as_str(), number) {
+            let address = binding.host_ip.clone().unwrap_or_else(|| "0.0.0.0".to_string());
+            let mut row = create_process_info(
+                protocol,
+                AddressFamily::from_host(&address),
+                address,
+                port.port.clone(),
+                "-".to_string(),
+                container.name.clone(),
+                format!("published by {}, no host process", container.engine),
+            );
+            set_container(&mut row, container);
+
+            let proxied = found.iter().any(|process| {
+                process.docker_container_id == container.id
+                    && process.protocol == protocol
+                    && same_address(&process.address, &row.address)
+            });
+            if port.matches(&row) && !proxied {
+                rows.push(row);
+            }
         }
     }
-    "unknown".to_string()
+    rows
 }
 
-fn create_process_info(port: String, pid: String, process_name: String, command: String) -> ProcessInfo {
-    let (docker_container_id, docker_image) = get_docker_info_from_command(&command);
+fn create_process_info(
+    protocol: Protocol,
+    family: AddressFamily,
+    address: String,
+    port: String,
+    pid: String,
+    process_name: String,
+    command: String,
+) -> ProcessInfo {
+    let holders = if pid.parse::<u32>().is_ok() {
+        vec![Holder {
+            pid: pid.clone(),
+            process_name: process_name.clone(),
+            role: HolderRole::Master,
+            start_time: None,
+            started: None,
+        }]
+    } else {
+        Vec::new()
+    };
     ProcessInfo {
         port,
+        protocol,
+        address,
+        family,
         pid,
+        holders,
         process_name,
         command,
-        docker_container_id,
-        docker_image,
+        docker_container_id: String::new(),
+        docker_image: String::new(),
+        docker_labels: BTreeMap::new(),
+        container_engine: None,
+        pod: String::new(),
+        compose_project: String::new(),
+        compose_service: String::new(),
+        compose_dir: String::new(),
+        unit: None,
+        managed_by: None,
+        netns: String::new(),
+        clients: None,
+        uid: None,
+        inode: None,
+        recv_q: None,
+        send_q: None,
+        v6only: None,
+        start_time: None,
+        sources: Vec::new(),
     }
 }
 
-fn list_all_processes(compact: bool, simple: bool, json_output: bool) {
-    let processes = get_processes_using_ports();
-    
+fn list_all_processes(options: &ScanOptions, unix: bool, compact: bool, simple: bool, json_output: bool) {
+    if unix {
+        let mut processes = procfs::scan_unix_listeners().unwrap_or_default();
+        resolve_and_enrich(&mut processes, options, &mut Snapshots::api_only());
+        if processes.is_empty() {
+            println!("No processes found listening on Unix sockets.");
+            return;
+        }
+        display_processes(&processes, compact, simple, json_output);
+        return;
+    }
+
+    let mut processes = get_processes_using_ports(options);
+    resolve_and_enrich(&mut processes, options, &mut Snapshots::api_only());
+
     if processes.is_empty() {
         println!("No processes found using ports.");
         return;
@@ -253,14 +1003,34 @@ fn list_all_processes(compact: bool, simple: bool, json_output: bool) {
     display_processes(&processes, compact, simple, json_output);
 }
 
-fn show_process_by_port(port: &str, compact: bool, simple: bool, json_output: bool) {
-    let processes = get_processes_using_ports();
-    let filtered: Vec<_> = processes.into_iter()
-        .filter(|p| p.port == port)
+fn get_processes_matching(port: &PortSpec, options: &ScanOptions, snapshots: &mut Snapshots) -> Vec<ProcessInfo> {
+    let processes = if port.is_unix() {
+        procfs::scan_unix_listeners().unwrap_or_default()
+    } else {
+        get_processes_using_ports(options)
+    };
+
+    let mut processes: Vec<ProcessInfo> = processes.into_iter()
+        .filter(|p| port.matches(p))
         .collect();
+    resolve_and_enrich(&mut processes, options, snapshots);
+    if !port.is_unix() {
+        let published = published_port_rows(port, &processes, &snapshots.all());
+        processes.extend(published);
+    }
+    processes
+}
+
+fn show_process_by_port(port: &PortSpec, options: &ScanOptions, compact: bool, simple: bool, json_output: bool) {
+    let filtered = get_processes_matching(port, options, &mut Snapshots::default());
 
     if filtered.is_empty() {
-        println!("No process found using port {}", port);
+        println!("No process found using {}", port.describe());
+        if let Some(path) = port.unix_path().filter(|path| is_stale_socket(path)) {
+            println!("{} is a stale socket file with no listener, remove it with: apps-ports -k {}", path, path);
+        } else if !port.is_unix() && !options.all_netns {
+            println!("Containers and sandboxed services may listen in another network namespace, try --all-netns");
+        }
         return;
     }
 
@@ -288,29 +1058,101 @@ fn display_processes(processes: &[ProcessInfo], compact: bool, simple: bool, jso
 fn display_simple_format(processes: &[ProcessInfo]) {
     for process in processes {
         let docker_info = if !process.docker_container_id.is_empty() {
-            format!(" [🐳 {}]", truncate_string(&process.docker_container_id, 8))
+            let compose = if process.compose_project.is_empty() {
+                String::new()
+            } else {
+                format!(" {}/{}", process.compose_project, process.compose_service)
+            };
+            let pod = if process.pod.is_empty() { String::new() } else { format!(" pod {}", process.pod) };
+            format!(" [🐳 {}{}{}]", truncate_string(&process.docker_container_id, 8), compose, pod)
+        } else {
+            "".to_string()
+        };
+        let workers = worker_count(process);
+        let netns_info = if !process.netns.is_empty() && process.netns != HOST_NAMESPACE {
+            format!(" [{}]", process.netns)
         } else {
             "".to_string()
         };
+        let unit_info = match &process.unit {
+            Some(unit) => format!(" [systemd: {}]", unit),
+            None => "".to_string(),
+        };
+        let manager_info = match &process.managed_by {
+            Some(app) => format!(" [{}]", app),
+            None => "".to_string(),
+        };
+        let worker_info = match workers {
+            0 => "".to_string(),
+            1 => " [+1 worker]".to_string(),
+            n => format!(" [+{} workers]", n),
+        };
         
-        println!("{}:{} {} ({}){}", 
-            process.port, 
+        println!("{}:{} {} ({}){}{}{}{}{}", 
+            format_endpoint(process),
             process.pid, 
             truncate_string(&process.process_name, 15),
             truncate_string(&process.command, 50),
-            docker_info
+            worker_info,
+            docker_info,
+            unit_info,
+            manager_info,
+            netns_info
         );
     }
 }
 
 fn display_compact_format(processes: &[ProcessInfo]) {
     for process in processes {
-        println!("Port: {}", process.port);
+        if process.protocol == Protocol::Unix {
+            println!("Socket: {}", process.address);
+        } else {
+            println!("Port: {}", process.port);
+            println!("  Protocol: {}", process.protocol);
+            println!("  Address: {} ({})", process.address, process.family);
+        }
+        if let Some(clients) = process.clients {
+            println!("  Clients: {}", clients);
+        }
         println!("  PID: {}", process.pid);
         println!("  Process: {}", process.process_name);
+        if process.holders.len() > 1 {
+            println!("  Holders:");
+            for holder in &process.holders {
+                println!("    {} {} ({})", holder.pid, holder.process_name, display_role(holder.role));
+            }
+        }
         if !process.docker_container_id.is_empty() {
-            println!("  Docker ID: {}", truncate_string(&process.docker_container_id, 12));
-            println!("  Docker Image: {}", process.docker_image);
+            let engine = process.container_engine.map_or("Docker", Engine::name);
+            println!("  {} ID: {}", engine, truncate_string(&process.docker_container_id, 12));
+            if !process.docker_image.is_empty() {
+                println!("  {} Image: {}", engine, process.docker_image);
+            }
+            if !process.pod.is_empty() {
+                println!("  Pod: {}", process.pod);
+            }
+            if !process.compose_project.is_empty() {
+                println!("  Compose: service {} of project {}", process.compose_service, process.compose_project);
+            }
+            if !process.compose_dir.is_empty() {
+                println!("  Compose directory: {}", process.compose_dir);
+            }
+        }
+        if let Some(unit) = &process.unit {
+            println!("  Systemd unit: {}", unit);
+            if !unit.activates.is_empty() {
+                println!("  Activates: {}", unit.activates.join(", "));
+            }
+        }
+        if let Some(app) = &process.managed_by {
+            println!("  Managed by: {} ({}, PID {})", app.name, app.manager, app.manager_pid);
+        }
+        if !process.netns.is_empty() {
+            println!("  Network namespace: {}", process.netns);
+        }
+        if !process.sources.is_empty() {
+            let names: Vec<&str> = process.sources.iter().map(|sighting| sighting.source).collect();
+            println!("  Sources: {}", names.join(", "));
         }
         println!("  Command: {}", truncate_string(&process.command, 80));
         println!();
@@ -321,7 +1163,12 @@ fn display_table_format(processes: &[ProcessInfo]) {
     // Create a version with truncated data for better table display
     let truncated_processes: Vec<ProcessInfo> = processes.iter().map(|p| ProcessInfo {
         port: p.port.clone(),
+        protocol: p.protocol,
+        address: p.address.clone(),
+        family: p.family,
+        clients: p.clients,
         pid: truncate_string(&p.pid, 8),
+        holders: p.holders.clone(),
         process_name: truncate_string(&p.process_name, 12),
         command: truncate_string(&p.command, 40),
         docker_container_id: if !p.docker_container_id.is_empty() {
@@ -330,310 +1177,1240 @@ fn display_table_format(processes: &[ProcessInfo]) {
             "".to_string()
         },
         docker_image: truncate_string(&p.docker_image, 20),
+        docker_labels: p.docker_labels.clone(),
+        container_engine: p.container_engine,
+        pod: p.pod.clone(),
+        compose_project: truncate_string(&p.compose_project, 16),
+        compose_service: truncate_string(&p.compose_service, 16),
+        compose_dir: truncate_string(&p.compose_dir, 24),
+        unit: p.unit.clone(),
+        managed_by: p.managed_by.clone(),
+        netns: truncate_string(&p.netns, 20),
+        uid: p.uid,
+        inode: p.inode,
+        recv_q: p.recv_q,
+        send_q: p.send_q,
+        v6only: p.v6only,
+        start_time: p.start_time,
+        sources: p.sources.clone(),
     }).collect();
     
     let mut table = Table::new(truncated_processes);
     table.with(Settings::default()
         .with(Modify::new(Columns::single(0)).with(Width::wrap(6)))   // port
-        .with(Modify::new(Columns::single(1)).with(Width::wrap(8)))   // pid
-        .with(Modify::new(Columns::single(2)).with(Width::wrap(12)))  // process_name
-        .with(Modify::new(Columns::single(3)).with(Width::wrap(40)))  // command
-        .with(Modify::new(Columns::single(4)).with(Width::wrap(12)))  // docker_id
-        .with(Modify::new(Columns::single(5)).with(Width::wrap(20)))  // docker_image
+        .with(Modify::new(Columns::single(1)).with(Width::wrap(5)))   // proto
+        .with(Modify::new(Columns::single(2)).with(Width::wrap(20)))  // address
+        .with(Modify::new(Columns::single(3)).with(Width::wrap(6)))   // family
+        .with(Modify::new(Columns::single(4)).with(Width::wrap(7)))   // clients
+        .with(Modify::new(Columns::single(5)).with(Width::wrap(8)))   // pid
+        .with(Modify::new(Columns::single(6)).with(Width::wrap(12).keep_words())) // workers
+        .with(Modify::new(Columns::single(7)).with(Width::wrap(12)))  // process_name
+        .with(Modify::new(Columns::single(8)).with(Width::wrap(40)))  // command
+        .with(Modify::new(Columns::single(9)).with(Width::wrap(12)))  // docker_id
+        .with(Modify::new(Columns::single(10)).with(Width::wrap(20))) // docker_image
+        .with(Modify::new(Columns::single(11)).with(Width::wrap(16))) // compose_project
+        .with(Modify::new(Columns::single(12)).with(Width::wrap(16))) // compose_service
+        .with(Modify::new(Columns::single(13)).with(Width::wrap(24))) // compose_dir
+        .with(Modify::new(Columns::single(14)).with(Width::wrap(20))) // unit
+        .with(Modify::new(Columns::single(15)).with(Width::wrap(20))) // managed_by
+        .with(Modify::new(Columns::single(16)).with(Width::wrap(20))) // netns
     );
+    hide_empty_columns(&mut table, processes);
     println!("{}", table);
 }
 
+/// Columns that only some setups fill in
+const OPTIONAL_COLUMNS: [&str; 8] =
+    ["clients", "workers", "compose_project", "compose_service", "compose_dir", "unit", "managed_by", "netns"];
+
+/// Leaves out the optional columns when no row has anything in them
+fn hide_empty_columns(table: &mut Table, processes: &[ProcessInfo]) {
+    for column in OPTIONAL_COLUMNS {
+        if !processes.iter().any(|process| has_value(process, column)) {
+            table.with(Disable::column(ByColumnName::new(column)));
+        }
+    }
+}
+
+fn has_value(process: &ProcessInfo, column: &str) -> bool {
+    match column {
+        "clients" => process.clients.is_some(),
+        "workers" => worker_count(process) > 0,
+        "compose_project" => !process.compose_project.is_empty(),
+        "compose_service" => !process.compose_service.is_empty(),
+        "compose_dir" => !process.compose_dir.is_empty(),
+        "unit" => process.unit.is_some(),
+        "managed_by" => process.managed_by.is_some(),
+        "netns" => !process.netns.is_empty(),
+        _ => true,
+    }
+}
+
+fn worker_count(process: &ProcessInfo) -> usize {
+    process.holders.iter().filter(|holder| holder.role == HolderRole::Worker).count()
+}
+
+fn display_workers(holders: &[Holder]) -> String {
+    holders
+        .iter()
+        .filter(|holder| holder.role == HolderRole::Worker)
+        .map(|holder| holder.pid.as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn display_role(role: HolderRole) -> &'static str {
+    match role {
+        HolderRole::Master => "master",
+        HolderRole::Worker => "worker",
+    }
+}
+
+fn display_unit(unit: &Option<SystemdUnit>) -> String {
+    unit.as_ref().map(|unit| unit.to_string()).unwrap_or_default()
+}
+
+fn display_managed_by(app: &Option<ManagedApp>) -> String {
+    app.as_ref().map(|app| app.to_string()).unwrap_or_default()
+}
+
+fn display_clients(clients: &Option<usize>) -> String {
+    clients.map(|count| count.to_string()).unwrap_or_default()
+}
+
+/// Shortens `s` to at most `max_len` characters, cutting between characters so that paths
+/// and commands in any script stay intact up to the "..."
 fn truncate_string(s: &str, max_len: usize) -> String {
-    if s.len() <= max_len {
+    if s.chars().count() <= max_len {
         s.to_string()
     } else {
-        format!("{}...", &s[..max_len.saturating_sub(3)])
+        format!("{}...", s.chars().take(max_len.saturating_sub(3)).collect::<String>())
     }
 }
 
-fn kill_process_by_port(port: &str, kill_docker: bool) {
-    let processes = get_processes_using_ports();
-    let filtered: Vec<_> = processes.into_iter()
-        .filter(|p| p.port == port)
-        .collect();
+/// Kills whatever holds `port`. Returns whether every kill that was asked for succeeded
+/// and the port ended up free, which becomes the exit code.
+fn kill_process_by_port(port: &PortSpec, kill_docker: bool, options: &ScanOptions, kill_options: &KillOptions) -> bool {
+    // Containers are listed once, for both the rows and the stops that follow
+    let mut snapshots = Snapshots::default();
+    let filtered = get_processes_matching(port, options, &mut snapshots);
 
     if filtered.is_empty() {
-        println!("No process found using port {}", port);
-        return;
+        println!("No process found using {}", port.describe());
+        return match port.unix_path() {
+            Some(path) => remove_stale_socket(path, kill_options),
+            None => true,
+        };
     }
 
-    println!("Found process(es) using port {}:", port);
-    let table = Table::new(&filtered);
+    let protected = ProtectedList::load();
+    println!("Found process(es) using {}:", port.describe());
+    let marked = protected.mark(&filtered);
+    let mut table = Table::new(&marked);
+    hide_empty_columns(&mut table, &marked);
     println!("{}", table);
 
+    // A pipe would answer every prompt with EOF, which reads as "no" and looks like success
+    if !kill_options.yes && !kill_options.dry_run && !io::stdin().is_terminal() {
+        println!("✗ Not killing anything: stdin is not a terminal, so there is nobody to confirm");
+        println!("  Pass --yes to kill without asking, or --dry-run to only show what would be done");
+        return false;
+    }
+
+    // PIDs signalled, and when the first signal went out, to check the port afterwards
+    let mut killed: Vec<u32> = Vec::new();
+    let mut started: Option<Instant> = None;
+    let mut succeeded = true;
+    // Only loaded for --scope, it reads every process on the system
+    let mut process_tree: Option<ProcessTree> = None;
+    // A container publishing on both IPv4 and IPv6 shows up twice, but is stopped once
+    let mut handled_containers: Vec<String> = Vec::new();
+
     for process in &filtered {
-        // Check if this is a docker-proxy process and we want to kill the container
-        if kill_docker && process.command.contains("docker-proxy") {
-            if let Some(container_id) = extract_container_id_from_docker_proxy(&process.command) {
-                print!("Kill Docker container {} (running on port {})? [y/N]: ", container_id, port);
-                io::stdout().flush().unwrap();
-                
-                if get_user_confirmation() {
-                    kill_docker_container(&container_id);
+        // Check if this is a container engine's port forwarder and we want to kill the container
+        // A port published without a host process can only be freed by stopping the container
+        let container_only = process.is_container_only();
+        if container_only || (kill_docker && process.container_engine.is_some()) {
+            if handled_containers.contains(&process.docker_container_id) {
+                continue;
+            }
+            let engine = process.container_engine.unwrap_or(Engine::Docker);
+            match snapshots.get(engine).map(|snapshot| (snapshot, container_for_row(snapshot, process))) {
+                Ok((snapshot, Some(container))) => {
+                    handled_containers.push(container.id.clone());
+                    let what = describe_container(container);
+                    if let Some(reason) = protected.reason_for_image(&container.image) {
+                        if !refuse_protected(&what, &reason, kill_options) {
+                            succeeded = false;
+                            continue;
+                        }
+                    }
+                    let published: Vec<String> = container
+                        .ports
+                        .iter()
+                        .filter(|binding| binding.host_port.is_some())
+                        .map(|binding| binding.to_string())
+                        .collect();
+                    if published.is_empty() {
+                        println!("{} runs {}", what, container.image);
+                    } else {
+                        println!("{} runs {}, publishing {}", what, container.image, published.join(", "));
+                    }
+                    if let Some(compose) = container.compose() {
+                        println!("{} is service {} of compose project {}", what, compose.service, compose.project);
+                        if let Some((stopped, ok)) =
+                            stop_compose_container(snapshot, container, &compose, &protected, kill_options, &mut started)
+                        {
+                            handled_containers.extend(stopped);
+                            succeeded &= ok;
+                            continue;
+                        }
+                    } else {
+                        if kill_options.dry_run {
+                            println!("Would stop {}", what);
+                            continue;
+                        }
+                        if confirm(&format!("Kill {} (running on port {})?", what, port), kill_options) {
+                            started.get_or_insert_with(Instant::now);
+                            succeeded &= kill_docker_container(container, kill_options);
+                            continue;
+                        }
+                    }
+                    if container_only {
+                        println!("Skipped stopping {}", what);
+                    }
+                }
+                Ok((_, None)) => println!("Could not find the container publishing {}", format_endpoint(process)),
+                Err(e) => println!("✗ Could not look up the container publishing {}: {}", format_endpoint(process), e),
+            }
+            if container_only {
+                succeeded = false;
+                continue;
+            }
+        }
+        
+        if let Some(clients) = process.clients.filter(|&clients| clients > 0) {
+            let noun = if clients == 1 { "connection" } else { "connections" };
+            println!("⚠ {} active {} on {}", clients, noun, format_endpoint(process));
+        }
+
+        if process.is_hidden() {
+            // PID 1's listeners are stopped through their .socket unit, no PID needed
+            if let Some(unit) = &process.unit {
+                if let Some(stopped) = stop_systemd_unit(process, unit, &protected, kill_options, &mut started) {
+                    succeeded &= stopped;
                     continue;
                 }
+            }
+            let endpoint = format_endpoint(process);
+            if privileged::is_root() {
+                println!("✗ Not killing the owner of {}: no process was found holding it, it may have closed since the scan", endpoint);
+            } else if options.sudo {
+                println!("✗ Not killing the owner of {}: it could not be resolved even with --sudo, so there is no PID to signal", endpoint);
             } else {
-                println!("Could not extract container ID from docker-proxy command");
+                println!("✗ Not killing the owner of {}: it is hidden from this user, so there is no PID to signal", endpoint);
+                println!("  Rerun with --sudo to look up the owner first, or run apps-ports as root");
             }
+            succeeded = false;
+            continue;
         }
-        
-        print!("Kill process {} (PID: {})? [y/N]: ", process.process_name, process.pid);
-        io::stdout().flush().unwrap();
-        
-        if get_user_confirmation() {
-            match StdCommand::new("kill")
-                .arg(&process.pid)
-                .output()
-            {
-                Ok(_) => println!("✓ Killed process {} (PID: {})", process.process_name, process.pid),
-                Err(e) => {
-                    println!("✗ Failed to kill process {}: {}", process.pid, e);
-                    // Try with sudo
-                    print!("Try with elevated privileges? [y/N]: ");
-                    io::stdout().flush().unwrap();
-                    if get_user_confirmation() {
-                        match StdCommand::new("sudo")
-                            .args(["kill", &process.pid])
-                            .output()
-                        {
-                            Ok(_) => println!("✓ Killed process {} (PID: {}) with sudo", process.process_name, process.pid),
-                            Err(e) => println!("✗ Failed to kill process {} even with sudo: {}", process.pid, e),
-                        }
-                    }
+
+        // Before the unit: supervisord may itself run as a service, and stopping that would
+        // take every program down with it
+        if let Some(app) = &process.managed_by {
+            if let Some((signalled, stopped)) = stop_managed_app(process, app, &protected, kill_options, &mut started) {
+                killed.extend(signalled);
+                succeeded &= stopped;
+                continue;
+            }
+        }
+
+        if let Some(unit) = &process.unit {
+            if let Some(stopped) = stop_systemd_unit(process, unit, &protected, kill_options, &mut started) {
+                killed.extend(process.holders.iter().filter_map(|holder| holder.pid.parse::<u32>().ok()).filter(|&pid| pid != 1));
+                succeeded &= stopped;
+                continue;
+            }
+        }
+
+        if kill_options.scope != KillScope::Process {
+            let tree = process_tree.get_or_insert_with(ProcessTree::load);
+            match kill_process_scope(process, tree, &protected, kill_options, &mut started) {
+                Some((signalled, stopped)) => {
+                    killed.extend(signalled);
+                    succeeded &= stopped;
                 }
+                None => succeeded &= kill_options.dry_run,
+            }
+            continue;
+        }
+
+        if let Some(reason) = protected.reason_for_row(process) {
+            let what = format!("{} (PID: {})", process.process_name, process.pid);
+            if !refuse_protected(&what, &reason, kill_options) {
+                succeeded = false;
+                continue;
             }
+        }
+
+        let chosen = if process.holders.len() > 1 {
+            choose_shared_socket_holders(process, kill_options)
+        } else if kill_options.dry_run
+            || confirm(&format!("Kill process {} (PID: {})?", process.process_name, process.pid), kill_options)
+        {
+            process.holders.iter().collect()
         } else {
             println!("Skipped killing process {} (PID: {})", process.process_name, process.pid);
+            succeeded = false;
+            Vec::new()
+        };
+        if chosen.is_empty() {
+            succeeded = false;
+        }
+
+        for holder in chosen {
+            if kill_options.dry_run {
+                describe_planned_kill(holder, kill_options);
+                continue;
+            }
+            started.get_or_insert_with(Instant::now);
+            match kill_pid(holder, kill_options) {
+                Some(stopped) => {
+                    killed.extend(holder.pid.parse::<u32>());
+                    succeeded &= stopped;
+                }
+                None => succeeded = false,
+            }
         }
     }
+
+    if let Some(started) = started {
+        succeeded &= release::verify_port_released(port, options, &filtered, &killed, started);
+    }
+    succeeded
 }
 
-fn try_ss_command() -> Option<Vec<ProcessInfo>> {
-    // Try ss with process info (requires elevated privileges for some processes)
-    for args in [["--tcp", "--listening", "--numeric", "--processes"].as_slice(), ["--tcp", "--listening", "--numeric"].as_slice()] {
-        let output = StdCommand::new("ss")
-            .args(args)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::null())
-            .output();
+/// Offers `systemctl stop` for a listener systemd manages, where killing the process would
+/// only get it restarted (or, for a socket unit, started again on the next connection).
+/// Only the service's main process counts: anything else in its cgroup (docker-proxy under
+/// docker.service, a dev server a CI runner started) is killed like any other process.
+/// Returns whether the unit stopped, or `None` to kill the process instead.
+fn stop_systemd_unit(
+    process: &ProcessInfo,
+    unit: &SystemdUnit,
+    protected: &ProtectedList,
+    kill_options: &KillOptions,
+    started: &mut Option<Instant>,
+) -> Option<bool> {
+    if unit.activates.is_empty() {
+        let state = systemd::service_state(unit)?;
+        let main_pid = state.main_pid?.to_string();
+        if !process.holders.iter().any(|holder| holder.pid == main_pid) {
+            return None;
+        }
+        if state.restarts {
+            println!("{} is run by systemd unit {}, which would restart a killed process", format_endpoint(process), unit);
+        } else {
+            println!("{} is the main process of systemd unit {}", format_endpoint(process), unit);
+        }
+    } else {
+        println!("{} is socket-activated by systemd unit {}", format_endpoint(process), unit);
+    }
+    if let Some(reason) = protected.reason_for_unit(unit, process) {
+        if !refuse_protected(&format!("systemd unit {}", unit), &reason, kill_options) {
+            return Some(false);
+        }
+    }
+
+    let units = unit.units_to_stop().join(" ");
+    let systemctl = if unit.user { "systemctl --user" } else { "systemctl" };
+    if kill_options.dry_run {
+        let elevation = if unit.user || privileged::is_root() { "" } else { " (with sudo, if polkit doesn't allow it)" };
+        println!("Would run: {} stop {}{}", systemctl, units, elevation);
+        return Some(true);
+    }
+    if !confirm(&format!("Stop {} with {}?", units, systemctl), kill_options) {
+        return None;
+    }
+
+    started.get_or_insert_with(Instant::now);
+    let message = match systemd::stop_unit(unit, false) {
+        Ok(()) => {
+            println!("✓ Stopped {}", units);
+            return Some(true);
+        }
+        Err(StopError::NotPermitted(message)) => message,
+        Err(StopError::Failed(message)) => {
+            println!("✗ Failed to stop {}: {}", units, message);
+            return Some(false);
+        }
+    };
 
-        if let Ok(output) = output {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let mut processes = Vec::new();
-            
-            for line in stdout.lines().skip(1) { // Skip header
-                if let Some(process_info) = parse_ss_line(line) {
-                    processes.push(process_info);
+    println!("✗ Not permitted to stop {}: {}", units, message);
+    if !confirm("Try with elevated privileges?", kill_options) {
+        return Some(false);
+    }
+    match systemd::stop_unit(unit, true) {
+        Ok(()) => {
+            println!("✓ Stopped {} with sudo", units);
+            Some(true)
+        }
+        Err(StopError::NotPermitted(message) | StopError::Failed(message)) => {
+            println!("✗ Failed to stop {} even with sudo: {}", units, message);
+            Some(false)
+        }
+    }
+}
+
+/// Offers the process manager's own stop command for a listener it runs, since it would
+/// restart a killed process (or, for foreman and honcho, stop the whole Procfile anyway).
+/// Returns the PIDs that should go away and whether stopping worked, or `None` to fall back
+/// to signalling the process: the user declined, or the manager's command failed.
+fn stop_managed_app(
+    process: &ProcessInfo,
+    app: &ManagedApp,
+    protected: &ProtectedList,
+    kill_options: &KillOptions,
+    started: &mut Option<Instant>,
+) -> Option<(Vec<u32>, bool)> {
+    let mut pids: Vec<u32> = process.holders.iter().filter_map(|holder| holder.pid.parse().ok()).collect();
+    let command = match &app.stop {
+        StopMethod::Command(command) => {
+            println!("{} is run by {} as {}, which would restart a killed process", format_endpoint(process), app.manager, app.name);
+            command
+        }
+        StopMethod::SignalManager => {
+            println!(
+                "{} is run by {} as {}, which stops everything it runs once one process exits",
+                format_endpoint(process),
+                app.manager,
+                app.name
+            );
+            let manager = Holder {
+                pid: app.manager_pid.to_string(),
+                process_name: app.manager.to_string(),
+                role: HolderRole::Master,
+                start_time: None,
+                started: app.manager_started,
+            };
+            if let Some(reason) = protected.reason(app.manager_pid, app.manager.name()) {
+                let what = format!("{} (PID: {})", app.manager, app.manager_pid);
+                if !refuse_protected(&what, &reason, kill_options) {
+                    return Some((Vec::new(), false));
                 }
             }
-            
-            if !processes.is_empty() {
-                return Some(processes);
+            if kill_options.dry_run {
+                describe_planned_kill(&manager, kill_options);
+                return Some((Vec::new(), true));
+            }
+            if !confirm(&format!("Stop {} (PID: {}) and everything it runs?", app.manager, app.manager_pid), kill_options) {
+                return None;
+            }
+            started.get_or_insert_with(Instant::now);
+            let stopped = kill_pid(&manager, kill_options).unwrap_or(false);
+            pids.push(app.manager_pid);
+            return Some((pids, stopped));
+        }
+    };
+
+    if let Some(reason) = protected.reason_for_app(app, process) {
+        if !refuse_protected(&format!("{} {}", app.manager, app.name), &reason, kill_options) {
+            return Some((Vec::new(), false));
+        }
+    }
+    if kill_options.dry_run {
+        println!("Would run: {}", command);
+        return Some((Vec::new(), true));
+    }
+    if !confirm(&format!("Stop {} with `{}`?", app.name, command), kill_options) {
+        return None;
+    }
+
+    started.get_or_insert_with(Instant::now);
+    let message = match command.run(false) {
+        Ok(()) => {
+            println!("✓ Stopped {} with {}", app.name, app.manager);
+            return Some((pids, true));
+        }
+        Err(message) => message,
+    };
+    println!("✗ `{}` failed: {}", command, message);
+
+    // The manager's control socket usually belongs to whoever started it
+    let other_user = app.manager_uid.is_some_and(|uid| uid != privileged::effective_uid());
+    if other_user && !privileged::is_root() && confirm("Try with elevated privileges?", kill_options) {
+        match command.run(true) {
+            Ok(()) => {
+                println!("✓ Stopped {} with {} using sudo", app.name, app.manager);
+                return Some((pids, true));
             }
+            Err(message) => println!("✗ `{}` failed even with sudo: {}", command, message),
         }
     }
+    println!("  Falling back to signalling the process; {} may restart it", app.manager);
     None
 }
 
-fn parse_ss_line(line: &str) -> Option<ProcessInfo> {
-    let parts: Vec<&str> = line.split_whitespace().collect();
-    if parts.len() >= 4 {
-        let local_address = parts[3];
-        
-        // Extract port from address (format: *:8080 or 0.0.0.0:8080 or [::]:8080)
-        let port = if let Some(colon_pos) = local_address.rfind(':') {
-            local_address[colon_pos + 1..].to_string()
-        } else {
-            return None;
-        };
-        
-        // Check if we have process info in the last column
-        if parts.len() >= 6 {
-            let process_info = parts[5];
-            if process_info.contains("users:") {
-                // Parse process info like: users:(("node",pid=12345,fd=10))
-                if let Some(pid_start) = process_info.find("pid=") {
-                    let pid_part = &process_info[pid_start + 4..];
-                    if let Some(pid_end) = pid_part.find(',') {
-                        let pid = pid_part[..pid_end].to_string();
-                        
-                        // Extract process name
-                        if let Some(name_start) = process_info.find('"') {
-                            if let Some(name_end) = process_info[name_start + 1..].find('"') {
-                                let process_name = process_info[name_start + 1..name_start + 1 + name_end].to_string();
-                                let command = get_command_by_pid(&pid);
-                                
-                                return Some(create_process_info(
-                                    port,
-                                    pid,
-                                    process_name,
-                                    command,
-                                ));
-                            }
-                        }
-                    }
-                }
+/// Says why a protected process or container is left alone, or, under --force, that it
+/// won't be. Returns whether to go ahead.
+fn refuse_protected(what: &str, reason: &str, kill_options: &KillOptions) -> bool {
+    if kill_options.force {
+        println!("⚠ {} is protected ({}), going ahead because of --force", what, reason);
+        return true;
+    }
+    println!("✗ Not killing {}: it is protected ({})", what, reason);
+    println!("  Pass --force to kill it anyway");
+    false
+}
+
+/// Asks a yes/no question, or answers it with yes under --yes
+fn confirm(question: &str, kill_options: &KillOptions) -> bool {
+    print!("{} [y/N]: ", question);
+    if kill_options.yes {
+        println!("y (--yes)");
+        return true;
+    }
+    io::stdout().flush().unwrap();
+    get_user_confirmation()
+}
+
+/// Killing a single worker of a pre-fork server doesn't free the port, so offer the
+/// master (which normally takes its workers down with it) or every holder
+fn choose_shared_socket_holders<'a>(process: &'a ProcessInfo, kill_options: &KillOptions) -> Vec<&'a Holder> {
+    println!("{} is shared by {} processes:", format_endpoint(process), process.holders.len());
+    for holder in &process.holders {
+        println!("  {} {} ({})", holder.pid, holder.process_name, display_role(holder.role));
+    }
+
+    print!("Kill [m]aster {} (PID: {}), [a]ll {} holders, or [N]one? ", process.process_name, process.pid, process.holders.len());
+    // Without a terminal to ask, go with the master: that's what frees the port in the common case
+    let answer = if kill_options.dry_run || kill_options.yes {
+        println!("m ({})", if kill_options.dry_run { "--dry-run" } else { "--yes" });
+        "m".to_string()
+    } else {
+        io::stdout().flush().unwrap();
+        read_user_input()
+    };
+
+    match answer.as_str() {
+        "m" | "master" => process.holders.iter().take(1).collect(),
+        "a" | "all" => process.holders.iter().collect(),
+        _ => {
+            println!("Skipped killing processes holding {}", format_endpoint(process));
+            Vec::new()
+        }
+    }
+}
+
+/// What --dry-run prints instead of signalling. Signal 0 tells whether sudo would be needed.
+fn describe_planned_kill(holder: &Holder, kill_options: &KillOptions) {
+    let (pid, process_name) = (&holder.pid, &holder.process_name);
+    let Ok(pid_number) = pid.parse::<u32>() else {
+        println!("✗ Can't kill {}: '{}' is not a PID", process_name, pid);
+        return;
+    };
+
+    let signal = kill_options.signal;
+    let with_sudo = match Target::open(pid_number, holder.started).and_then(|target| target.probe()) {
+        Ok(()) => false,
+        Err(SignalError::NotPermitted) => true,
+        Err(SignalError::NoSuchProcess) => {
+            println!("Process {} (PID: {}) has already exited, nothing to do", process_name, pid);
+            return;
+        }
+        Err(e) => {
+            println!("✗ Can't signal process {} (PID: {}): {}", process_name, pid, e);
+            return;
+        }
+    };
+
+    let how = if with_sudo {
+        format!(" with sudo (it belongs to another user): sudo kill -{} {}", signal.number, pid)
+    } else {
+        String::new()
+    };
+    println!("Would send {} to process {} (PID: {}){}", signal, process_name, pid, how);
+    if kill_options.escalates() {
+        println!("  then {} if it's still running after {:.1}s", SIGKILL, kill_options.grace.as_secs_f64());
+    }
+}
+
+/// Kills what --scope asks for around a listener (its process group, its subtree, or
+/// everything its launcher started) after showing it as a tree. Returns the PIDs that
+/// were signalled and whether they all stopped, or `None` when nothing was signalled.
+fn kill_process_scope(
+    process: &ProcessInfo,
+    tree: &ProcessTree,
+    protected: &ProtectedList,
+    kill_options: &KillOptions,
+    started: &mut Option<Instant>,
+) -> Option<(Vec<u32>, bool)> {
+    let listeners: Vec<u32> = process.holders.iter().filter_map(|holder| holder.pid.parse().ok()).collect();
+    let pid = *listeners.first()?;
+    // Never take down apps-ports itself or the shell (or make, or git hook) running it
+    let own = tree.ancestors(std::process::id());
+
+    let (mut pids, group) = match kill_options.scope {
+        KillScope::Group => {
+            let Some(pgid) = tree.get(pid).and_then(|meta| meta.pgid) else {
+                println!("✗ Can't find the process group of {} (PID: {})", process.process_name, pid);
+                return None;
+            };
+            let members = tree.group(pgid);
+            if members.iter().any(|member| own.contains(member)) {
+                println!("✗ Not killing process group {}: apps-ports is running in it, use --scope tree instead", pgid);
+                return None;
             }
+            (members, Some(pgid))
         }
-        
-        // If no process info, try to find it by port using lsof
-        if let Some(process_info) = find_process_by_port(&port) {
-            return Some(process_info);
+        KillScope::Tree => (tree.subtree(pid), None),
+        KillScope::Launcher => (tree.subtree(tree.launcher(pid, &own)), None),
+        KillScope::Process => (listeners.clone(), None),
+    };
+    pids.retain(|pid| !own.contains(pid));
+
+    // Pinned before asking, the tree may look different by the time the user answers
+    let mut targets = Vec::new();
+    for pid in pids {
+        match Target::open(pid, tree.get(pid).and_then(|meta| meta.started)) {
+            Ok(target) => targets.push(target),
+            Err(SignalError::NoSuchProcess) => {}
+            Err(e) => println!("⚠ Leaving out PID {}: {}", pid, e),
         }
-        
-        // Return basic info without process details
-        return Some(create_process_info(
-            port,
-            "hidden".to_string(),
-            "(elevated privileges required)".to_string(),
-            "Run with 'sudo' to see process details".to_string(),
-        ));
     }
-    None
+    if targets.is_empty() {
+        return None;
+    }
+    let pids: Vec<u32> = targets.iter().map(|target| target.pid).collect();
+
+    let endpoint = format_endpoint(process);
+    match group {
+        Some(pgid) => println!("Process group {} holding {}:", pgid, endpoint),
+        None => println!("Process tree holding {}:", endpoint),
+    }
+    for line in tree.render(&pids, &listeners, &format!("listening on {}", endpoint)) {
+        println!("  {}", line);
+    }
+
+    let mut allowed = true;
+    for &pid in &pids {
+        let name = tree.get(pid).map(|meta| meta.comm.as_str()).unwrap_or_default();
+        if let Some(reason) = protected.reason(pid, name) {
+            allowed &= refuse_protected(&format!("{} (PID: {})", name, pid), &reason, kill_options);
+        }
+    }
+    if !allowed {
+        return None;
+    }
+
+    let noun = if targets.len() == 1 { "process" } else { "processes" };
+    let what = match group {
+        Some(pgid) => format!("process group {} ({} {})", pgid, targets.len(), noun),
+        None if targets.len() == 1 => "this process".to_string(),
+        None => format!("these {} processes", targets.len()),
+    };
+    let signal = kill_options.signal;
+    if kill_options.dry_run {
+        let needs_sudo = targets
+            .iter()
+            .filter(|target| matches!(target.probe(), Err(SignalError::NotPermitted)))
+            .count();
+        let how = if needs_sudo > 0 { format!(" ({} of them with sudo, they belong to another user)", needs_sudo) } else { String::new() };
+        println!("Would send {} to {}{}", signal, what, how);
+        if kill_options.escalates() {
+            println!("  then {} to those still running after {:.1}s", SIGKILL, kill_options.grace.as_secs_f64());
+        }
+        return None;
+    }
+    if !confirm(&format!("Kill {}?", what), kill_options) {
+        println!("Skipped killing processes holding {}", endpoint);
+        return None;
+    }
+
+    // Parents go first, so a wrapper can't restart the children it sees dying
+    started.get_or_insert_with(Instant::now);
+    let (delivered, with_sudo) = signal_targets(&targets, group, signal, kill_options)?;
+    if !kill_options.escalates() {
+        return Some((pids, delivered));
+    }
+
+    let waited = Instant::now();
+    let survivors = signal::wait_for_all(&targets, kill_options.grace);
+    if survivors.is_empty() {
+        println!("✓ All {} {} exited after {:.1}s", targets.len(), noun, waited.elapsed().as_secs_f64());
+        return Some((pids, delivered));
+    }
+
+    let survivor_list: Vec<String> = survivors.iter().map(|target| target.pid.to_string()).collect();
+    println!(
+        "⚠ PID {} still running {:.1}s after {}, sending {}",
+        survivor_list.join(", "),
+        kill_options.grace.as_secs_f64(),
+        signal,
+        SIGKILL
+    );
+    let sent = survivors.iter().all(|survivor| match survivor.send(SIGKILL) {
+        Ok(()) | Err(SignalError::NoSuchProcess) | Err(SignalError::Replaced) => true,
+        Err(SignalError::NotPermitted) if with_sudo => {
+            !survivor.is_alive() || run_sudo_kill(&[survivor.pid.to_string()], SIGKILL)
+        }
+        Err(e) => {
+            println!("✗ Failed to send {} to PID {}: {}", SIGKILL, survivor.pid, e);
+            false
+        }
+    });
+
+    let stuck: Vec<u32> = survivors
+        .iter()
+        .filter(|survivor| survivor.wait_for_exit(Duration::from_secs(2)).is_none())
+        .map(|survivor| survivor.pid)
+        .collect();
+    if stuck.is_empty() && sent {
+        println!("✓ Killed PID {} with {}", survivor_list.join(", "), SIGKILL);
+        return Some((pids, delivered));
+    }
+    for pid in stuck {
+        println!("✗ Process PID {} survived {}, it may be stuck in the kernel", pid, SIGKILL);
+    }
+    Some((pids, false))
 }
 
-fn find_process_by_port(port: &str) -> Option<ProcessInfo> {
-    // Try lsof first
-    let output = StdCommand::new("lsof")
-        .args(["-i", &format!(":{}", port), "-P", "-n"])
-        .stdout(Stdio::piped())
-        .stderr(Stdio::null())
-        .output();
+/// Sends the first signal to a process group or to each target in turn, falling back to
+/// sudo for those owned by another user. Returns whether every signal was delivered and
+/// whether sudo was needed, or `None` when nothing could be signalled.
+fn signal_targets(targets: &[Target], group: Option<u32>, signal: Signal, kill_options: &KillOptions) -> Option<(bool, bool)> {
+    let mut delivered = true;
+    let mut not_permitted: Vec<String> = Vec::new();
 
-    if let Ok(output) = output {
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        for line in stdout.lines().skip(1) {
-            if line.contains("LISTEN") {
-                return parse_lsof_line(line);
+    match group {
+        // A group ID is only reused once every member is gone, so a pinned member that's
+        // still alive means it's the same group
+        Some(pgid) if !targets.iter().any(Target::is_alive) => {
+            println!("✓ Process group {} has already exited", pgid);
+            return Some((true, false));
+        }
+        Some(pgid) => match signal::send_signal_to_group(pgid, signal) {
+            Ok(()) => println!("✓ Sent {} to process group {}", signal, pgid),
+            Err(SignalError::NoSuchProcess) => {
+                println!("✓ Process group {} has already exited", pgid);
+                return Some((true, false));
+            }
+            // kill(1) takes a negative number for a group
+            Err(SignalError::NotPermitted) => not_permitted.push(format!("-{}", pgid)),
+            Err(e) => {
+                println!("✗ Failed to signal process group {}: {}", pgid, e);
+                return None;
+            }
+        },
+        None => {
+            let mut sent = 0;
+            for target in targets {
+                match target.send(signal) {
+                    Ok(()) => sent += 1,
+                    Err(SignalError::NoSuchProcess) => {}
+                    Err(SignalError::NotPermitted) => not_permitted.push(target.pid.to_string()),
+                    Err(e) => {
+                        println!("✗ Failed to signal PID {}: {}", target.pid, e);
+                        delivered = false;
+                    }
+                }
+            }
+            if sent > 0 {
+                println!("✓ Sent {} to {} of {} processes", signal, sent, targets.len());
             }
         }
     }
-    
-    // If lsof didn't work, try to find the process using fuser
-    let output = StdCommand::new("fuser")
-        .args([&format!("{}/tcp", port)])
-        .stdout(Stdio::piped())
-        .stderr(Stdio::null())
-        .output();
-        
-    if let Ok(output) = output {
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        for word in stdout.split_whitespace() {
-            if let Ok(pid) = word.parse::<u32>() {
-                let pid_str = pid.to_string();
-                let process_name = get_process_name_by_pid(&pid_str);
-                let command = get_command_by_pid(&pid_str);
-                
-                return Some(create_process_info(
-                    port.to_string(),
-                    pid_str,
-                    process_name,
-                    command,
-                ));
+
+    if not_permitted.is_empty() {
+        return Some((delivered, false));
+    }
+    println!("✗ Not permitted to signal {}, owned by another user", not_permitted.join(", "));
+    if confirm("Try with elevated privileges?", kill_options) && run_sudo_kill(&not_permitted, signal) {
+        println!("✓ Sent {} to {} with sudo", signal, not_permitted.join(", "));
+        return Some((delivered, true));
+    }
+    (group.is_none() && not_permitted.len() < targets.len()).then_some((false, false))
+}
+
+/// Signals a process, escalating when configured to. Returns `None` when it couldn't be
+/// signalled, otherwise whether it is known to have stopped: without escalation the
+/// signal going out is all that's checked.
+fn kill_pid(holder: &Holder, kill_options: &KillOptions) -> Option<bool> {
+    let (pid, process_name) = (&holder.pid, &holder.process_name);
+    let Ok(pid_number) = pid.parse::<u32>() else {
+        println!("✗ Can't kill {}: '{}' is not a PID", process_name, pid);
+        return None;
+    };
+
+    // Pinning the process checks it's still the one the scan found, and with a pidfd it
+    // can't be mixed up with a newer process reusing the PID however long the prompt took
+    let target = match Target::open(pid_number, holder.started) {
+        Ok(target) => target,
+        Err(e) => return report_unsignalled(holder, e),
+    };
+
+    let signal = kill_options.signal;
+    let with_sudo = match target.send(signal) {
+        Ok(()) => {
+            println!("✓ Sent {} to process {} (PID: {})", signal, process_name, pid);
+            false
+        }
+        Err(SignalError::NotPermitted) => {
+            println!("✗ Not permitted to signal process {} (PID: {}), it belongs to another user", process_name, pid);
+            if !confirm("Try with elevated privileges?", kill_options) || !kill_pid_with_sudo(&target, process_name, signal) {
+                return None;
             }
+            true
         }
+        Err(e) => return report_unsignalled(holder, e),
+    };
+
+    if kill_options.escalates() {
+        return Some(escalate_if_alive(&target, process_name, kill_options, with_sudo));
     }
-    
-    None
+    Some(true)
 }
 
-fn extract_container_id_from_docker_proxy(command: &str) -> Option<String> {
-    // Docker-proxy command format:
-    // /usr/bin/docker-proxy -proto tcp -host-ip 0.0.0.0 -host-port 8080 -container-ip 172.17.0.2 -container-port 8080
-    if let Some(container_ip_pos) = command.find("-container-ip ") {
-        let after_container_ip = &command[container_ip_pos + 14..];
-        if let Some(space_pos) = after_container_ip.find(' ') {
-            let container_ip = &after_container_ip[..space_pos];
-            
-            // Find container ID by IP address
-            return find_container_by_ip(container_ip);
+/// Explains why a process wasn't signalled, returning like `kill_pid`: one that is
+/// already gone counts as stopped
+fn report_unsignalled(holder: &Holder, error: SignalError) -> Option<bool> {
+    match error {
+        // Already stopped, which is what was asked for
+        SignalError::NoSuchProcess => {
+            println!("✓ Process {} (PID: {}) has already exited", holder.process_name, holder.pid);
+            Some(true)
+        }
+        SignalError::Replaced => {
+            println!(
+                "✓ Process {} (PID: {}) has already exited, leaving alone the new process that reused its PID",
+                holder.process_name, holder.pid
+            );
+            Some(true)
+        }
+        e => {
+            println!("✗ Failed to signal process {} (PID: {}): {}", holder.process_name, holder.pid, e);
+            None
         }
     }
-    None
 }
 
-fn find_container_by_ip(container_ip: &str) -> Option<String> {
-    let output = StdCommand::new("docker")
-        .args(["ps", "--format", "{{.ID}} {{.Names}}", "--no-trunc"])
-        .output();
-        
-    if let Ok(output) = output {
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        for line in stdout.lines() {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if let Some(container_id) = parts.first() {
-                // Check if this container has the matching IP
-                if let Some(ip) = get_container_ip(container_id) {
-                    if ip == container_ip {
-                        return Some(container_id.to_string());
-                    }
-                }
+/// Gives the process the grace period to exit after a catchable signal, then sends
+/// SIGKILL, so servers that ignore SIGTERM still go down in one run. Returns whether
+/// the process is gone.
+fn escalate_if_alive(target: &Target, process_name: &str, kill_options: &KillOptions, with_sudo: bool) -> bool {
+    let pid = target.pid;
+    if let Some(elapsed) = target.wait_for_exit(kill_options.grace) {
+        println!("✓ Process {} (PID: {}) exited after {:.1}s", process_name, pid, elapsed.as_secs_f64());
+        return true;
+    }
+
+    println!(
+        "⚠ Process {} (PID: {}) is still running {:.1}s after {}, sending {}",
+        process_name,
+        pid,
+        kill_options.grace.as_secs_f64(),
+        kill_options.signal,
+        SIGKILL
+    );
+    let sent = if with_sudo {
+        kill_pid_with_sudo(target, process_name, SIGKILL)
+    } else {
+        match target.send(SIGKILL) {
+            Ok(()) | Err(SignalError::NoSuchProcess) | Err(SignalError::Replaced) => true,
+            Err(e) => {
+                println!("✗ Failed to send {} to process {} (PID: {}): {}", SIGKILL, process_name, pid, e);
+                false
             }
         }
+    };
+
+    if !sent {
+        return false;
+    }
+    match target.wait_for_exit(Duration::from_secs(2)) {
+        Some(_) => {
+            println!("✓ Killed process {} (PID: {}) with {}", process_name, pid, SIGKILL);
+            true
+        }
+        None => {
+            println!("✗ Process {} (PID: {}) survived {}, it may be stuck in the kernel", process_name, pid, SIGKILL);
+            false
+        }
     }
-    None
 }
 
-fn get_container_ip(container_id: &str) -> Option<String> {
-    let output = StdCommand::new("docker")
-        .args(["inspect", "-f", "{{range.NetworkSettings.Networks}}{{.IPAddress}}{{end}}", container_id])
+fn kill_pid_with_sudo(target: &Target, process_name: &str, signal: Signal) -> bool {
+    // sudo kill goes by PID, so check right before that the process is still the one pinned
+    if !target.is_alive() {
+        println!("✓ Process {} (PID: {}) has exited in the meantime", process_name, target.pid);
+        return true;
+    }
+    let sent = run_sudo_kill(&[target.pid.to_string()], signal);
+    if sent {
+        println!("✓ Sent {} to process {} (PID: {}) with sudo", signal, process_name, target.pid);
+    }
+    sent
+}
+
+/// Runs `sudo kill` on PIDs, or on negative process group IDs
+fn run_sudo_kill(targets: &[String], signal: Signal) -> bool {
+    let output = privileged::sudo_command()
+        .args(["kill", &format!("-{}", signal.number), "--"])
+        .args(targets)
+        .stdin(Stdio::inherit())
         .output();
-        
-    if let Ok(output) = output {
-        let ip = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        if !ip.is_empty() {
-            return Some(ip);
+
+    match output {
+        Ok(output) if output.status.success() => true,
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            println!("✗ Failed to kill {} even with sudo: {}", targets.join(", "), stderr.trim());
+            false
+        }
+        Err(e) => {
+            println!("✗ Failed to run sudo: {}", e);
+            false
         }
     }
-    None
 }
 
-fn kill_docker_container(container_id: &str) {
-    println!("Stopping Docker container: {}", container_id);
-    
-    match StdCommand::new("docker")
-        .args(["stop", container_id])
-        .output()
-    {
-        Ok(output) => {
-            if output.status.success() {
-                println!("✓ Successfully stopped Docker container {}", container_id);
-                
-                // Ask if user wants to remove the container
-                print!("Remove the stopped container? [y/N]: ");
-                io::stdout().flush().unwrap();
-                
-                if get_user_confirmation() {
-                    match StdCommand::new("docker")
-                        .args(["rm", container_id])
-                        .output()
-                    {
-                        Ok(_) => println!("✓ Removed Docker container {}", container_id),
-                        Err(e) => println!("✗ Failed to remove container {}: {}", container_id, e),
-                    }
-                }
-            } else {
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                println!("✗ Failed to stop container {}: {}", container_id, stderr);
+/// What rows and connections whose process can't be seen show as its name. As root every
+/// process can be seen, so there was none to find.
+fn hidden_process_name() -> &'static str {
+    if privileged::is_root() {
+        "(unknown)"
+    } else {
+        "(elevated privileges required)"
+    }
+}
+
+/// What rows and connections whose process can't be seen show as its command: rerunning
+/// with --sudo only helps when it wasn't given and we aren't root already
+fn hidden_command(sudo: bool) -> &'static str {
+    if privileged::is_root() {
+        "No process found, the socket may have closed since the scan"
+    } else if sudo {
+        "Could not be resolved even with --sudo"
+    } else {
+        "Run with --sudo to see process details"
+    }
+}
+
+fn hidden_process_info(protocol: Protocol, family: AddressFamily, address: String, port: String) -> ProcessInfo {
+    // Return basic info without process details
+    create_process_info(
+        protocol,
+        family,
+        address,
+        port,
+        "hidden".to_string(),
+        hidden_process_name().to_string(),
+        hidden_command(false).to_string(),
+    )
+}
+
+/// The container behind a forwarder row or a port published with no host process
+fn container_for_row<'a>(snapshot: &'a ContainerSnapshot, process: &ProcessInfo) -> Option<&'a Container> {
+    if !process.docker_container_id.is_empty() {
+        return snapshot.get(&process.docker_container_id);
+    }
+    container_for_forwarder(snapshot, process)
+}
+
+/// Offers to stop a Compose container's service, or its whole project, with `docker compose`
+/// from the project's directory, rather than only the one container: other replicas and
+/// the rest of the stack would keep running. Returns the IDs of the containers it stopped
+/// and whether that worked, or `None` when the user skipped it.
+fn stop_compose_container(
+    snapshot: &ContainerSnapshot,
+    container: &Container,
+    compose: &ComposeService,
+    protected: &ProtectedList,
+    kill_options: &KillOptions,
+    started: &mut Option<Instant>,
+) -> Option<(Vec<String>, bool)> {
+    let count = |containers: &[&Container]| match containers.len() {
+        1 => "1 container".to_string(),
+        n => format!("{} containers", n),
+    };
+    let service_containers = snapshot.compose_containers(&compose.project, Some(&compose.service));
+    let project_containers = snapshot.compose_containers(&compose.project, None);
+
+    print!(
+        "Stop [s]ervice {} ({}), the whole [p]roject {} ({}), just the [c]ontainer, or [N]one? ",
+        compose.service,
+        count(&service_containers),
+        compose.project,
+        count(&project_containers)
+    );
+    // Without a terminal to ask, stop the service: it frees the port without taking down
+    // the rest of the stack
+    let answer = if kill_options.dry_run || kill_options.yes {
+        println!("s ({})", if kill_options.dry_run { "--dry-run" } else { "--yes" });
+        "s".to_string()
+    } else {
+        io::stdout().flush().unwrap();
+        read_user_input()
+    };
+
+    let (whole_project, containers) = match answer.as_str() {
+        "s" | "service" => (false, service_containers),
+        "p" | "project" => (true, project_containers),
+        "c" | "container" => {
+            started.get_or_insert_with(Instant::now);
+            return Some((vec![container.id.clone()], kill_docker_container(container, kill_options)));
+        }
+        _ => return None,
+    };
+    // The container behind the port has been checked already, but a project or a service
+    // with other images in it can take a protected database down along with it
+    let mut allowed = true;
+    for other in containers.iter().filter(|other| other.id != container.id) {
+        if let Some(reason) = protected.reason_for_image(&other.image) {
+            allowed &= refuse_protected(&describe_container(other), &reason, kill_options);
+        }
+    }
+    if !allowed {
+        return Some((Vec::new(), false));
+    }
+    let ids: Vec<String> = containers.iter().map(|container| container.id.clone()).collect();
+    let command = compose.stop_command(whole_project).join(" ");
+    let location = compose.working_dir.as_ref().map(|dir| format!(" (in {})", dir)).unwrap_or_default();
+    if kill_options.dry_run {
+        println!("Would run: {}{}", command, location);
+        return Some((ids, true));
+    }
+
+    started.get_or_insert_with(Instant::now);
+    let what = if whole_project {
+        format!("compose project {}", compose.project)
+    } else {
+        format!("service {} of compose project {}", compose.service, compose.project)
+    };
+    match compose.run(&compose.stop_command(whole_project)) {
+        Ok(()) => {
+            println!("✓ Stopped {}", what);
+            return Some((ids, true));
+        }
+        Err(message) => println!("⚠ `{}` failed: {}", command, message),
+    }
+
+    // Without the CLI or the compose file, the containers can still be stopped one by one
+    println!("  Stopping the containers of {} through the {} API instead", what, container.engine.name());
+    let mut stopped = true;
+    for container in containers {
+        match docker::stop_container(container) {
+            Ok(()) => println!("✓ Stopped {}", describe_container(container)),
+            Err(e) => {
+                println!("✗ Failed to stop {}: {}", describe_container(container), e);
+                stopped = false;
             }
         }
-        Err(e) => println!("✗ Failed to execute docker stop: {}", e),
+    }
+    Some((ids, stopped))
+}
+
+/// "Docker container web (82fee02d1c3a)", or only the short ID for a container without a
+/// name. A Podman container in a pod is stopped with the pod, so it goes by the pod's name.
+fn describe_container(container: &Container) -> String {
+    let engine = container.engine.name();
+    if let Some(pod) = &container.pod {
+        return format!("{} pod {}", engine, pod);
+    }
+    let id = container.short_id();
+    if container.name.is_empty() {
+        format!("{} container {}", engine, id)
+    } else {
+        format!("{} container {} ({})", engine, container.name, id)
+    }
+}
+
+fn kill_docker_container(container: &Container, kill_options: &KillOptions) -> bool {
+    let name = describe_container(container);
+    let noun = if container.pod.is_some() { "pod" } else { "container" };
+    println!("Stopping {}", name);
+
+    if let Err(e) = docker::stop_container(container) {
+        println!("✗ Failed to stop {}: {}", name, e);
+        return false;
+    }
+    println!("✓ Successfully stopped {}", name);
+
+    // Removing is never implied by --yes, it throws away the container's state
+    if kill_options.yes {
+        println!("  The stopped {} is kept, remove it with: {}", noun, container.remove_command());
+        return true;
+    }
+
+    // Ask if user wants to remove the container
+    print!("Remove the stopped {}? [y/N]: ", noun);
+    io::stdout().flush().unwrap();
+
+    if get_user_confirmation() {
+        match docker::remove_container(container) {
+            Ok(()) => println!("✓ Removed {}", name),
+            Err(e) => println!("✗ Failed to remove {}: {}", name, e),
+        }
+    }
+    true
+}
+
+/// A socket file nobody accepts connections on, typically left behind by a crashed server
+#[cfg(unix)]
+fn is_stale_socket(path: &str) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::UnixStream;
+
+    // Abstract sockets vanish with their owner, so only files can go stale
+    let is_socket_file = std::fs::symlink_metadata(path)
+        .map(|metadata| metadata.file_type().is_socket())
+        .unwrap_or(false);
+
+    // A listener in another network namespace is invisible in /proc/net/unix but still
+    // accepts connections, so connecting is the reliable test
+    is_socket_file
+        && matches!(UnixStream::connect(path), Err(e) if e.kind() == io::ErrorKind::ConnectionRefused)
+}
+
+#[cfg(not(unix))]
+fn is_stale_socket(_path: &str) -> bool {
+    false
+}
+
+fn remove_stale_socket(path: &str, kill_options: &KillOptions) -> bool {
+    if !is_stale_socket(path) {
+        return true;
+    }
+
+    if kill_options.dry_run {
+        println!("Would remove stale socket file {}", path);
+        return true;
+    }
+    if !kill_options.yes && !io::stdin().is_terminal() {
+        println!("✗ {} is a stale socket file, not removing it without a terminal to confirm (pass --yes)", path);
+        return false;
+    }
+
+    if confirm(&format!("{} is a stale socket file with no listener. Remove it?", path), kill_options) {
+        match std::fs::remove_file(path) {
+            Ok(_) => {
+                println!("✓ Removed stale socket file {}", path);
+                true
+            }
+            Err(e) => {
+                println!("✗ Failed to remove socket file {}: {}", path, e);
+                false
+            }
+        }
+    } else {
+        println!("Skipped removing socket file {}", path);
+        false
     }
 }
 
 fn get_user_confirmation() -> bool {
+    let input = read_user_input();
+    input == "y" || input == "yes"
+}
+
+fn read_user_input() -> String {
     let mut input = String::new();
     match io::stdin().read_line(&mut input) {
-        Ok(_) => {
-            let input = input.trim().to_lowercase();
-            input == "y" || input == "yes"
-        }
-        Err(_) => false,
+        Ok(_) => input.trim().to_lowercase(),
+        Err(_) => String::new(),
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listener(protocol: Protocol, address: &str, port: &str) -> ProcessInfo {
+        let family = AddressFamily::from_host(address);
+        create_process_info(protocol, family, address.to_string(), port.to_string(), "1".to_string(), String::new(), String::new())
+    }
+
+    #[test]
+    fn parses_port_specs() {
+        let spec = PortSpec::parse("[::1]:80").unwrap();
+        assert_eq!((spec.address.as_deref(), spec.port.as_str(), spec.protocol), (Some("::1"), "80", None));
+
+        let spec = PortSpec::parse(":80/udp").unwrap();
+        assert_eq!((spec.address.as_deref(), spec.port.as_str(), spec.protocol), (None, "80", Some(Protocol::Udp)));
+
+        let spec = PortSpec::parse("0.0.0.0:80").unwrap();
+        assert_eq!((spec.address.as_deref(), spec.port.as_str(), spec.protocol), (Some("0.0.0.0"), "80", None));
+
+        let spec = PortSpec::parse("/path.sock").unwrap();
+        assert_eq!((spec.unix_path(), spec.protocol), (Some("/path.sock"), Some(Protocol::Unix)));
+    }
+
+    #[test]
+    fn rejects_bad_port_specs() {
+        assert!(PortSpec::parse("localhost:80").is_err());
+        assert!(PortSpec::parse("80/sctp").is_err());
+        assert!(PortSpec::parse("[::1]:http").is_err());
+    }
+
+    #[test]
+    fn leading_zeros_match_the_listener_port() {
+        let spec = PortSpec::parse("03000").unwrap();
+        assert_eq!(spec.port, "3000");
+        assert!(spec.matches(&listener(Protocol::Tcp, "0.0.0.0", "3000")));
+    }
+
+    #[test]
+    fn specific_address_matches_wildcard_listener() {
+        let spec = PortSpec::parse("127.0.0.1:18080").unwrap();
+        assert!(spec.matches(&listener(Protocol::Tcp, "0.0.0.0", "18080")));
+        assert!(spec.matches(&listener(Protocol::Tcp, "127.0.0.1", "18080")));
+        assert!(!spec.matches(&listener(Protocol::Tcp, "127.0.0.2", "18080")));
+        assert!(!spec.matches(&listener(Protocol::Tcp, "::1", "18080")));
+
+        let spec = PortSpec::parse("[::1]:80").unwrap();
+        assert!(spec.matches(&listener(Protocol::Tcp, "::", "80")));
+        assert!(spec.matches(&listener(Protocol::Tcp, "::1", "80")));
+        assert!(!spec.matches(&listener(Protocol::Tcp, "0.0.0.0", "80")));
+
+        // A dual-stack [::] listener takes IPv4 clients as IPv4-mapped addresses
+        assert!(PortSpec::parse("[::ffff:127.0.0.1]:80").unwrap().matches(&listener(Protocol::Tcp, "::", "80")));
+    }
+
+    #[test]
+    fn ipv4_spec_matches_dual_stack_listener() {
+        let spec = PortSpec::parse("127.0.0.1:40600").unwrap();
+        let mut any = listener(Protocol::Tcp, "::", "40600");
+        assert!(spec.matches(&any));
+        any.v6only = Some(false);
+        assert!(spec.matches(&any));
+        any.v6only = Some(true);
+        assert!(!spec.matches(&any));
+        assert!(PortSpec::parse("[::1]:40600").unwrap().matches(&any));
+    }
+
+    #[test]
+    fn wildcard_spec_matches_listeners_of_its_family() {
+        let spec = PortSpec::parse("0.0.0.0:80").unwrap();
+        assert!(spec.matches(&listener(Protocol::Tcp, "0.0.0.0", "80")));
+        assert!(spec.matches(&listener(Protocol::Udp, "127.0.0.1", "80")));
+        assert!(spec.matches(&listener(Protocol::Tcp, "::", "80")));
+        assert!(!spec.matches(&listener(Protocol::Tcp, "::1", "80")));
+        assert!(!spec.matches(&listener(Protocol::Tcp, "0.0.0.0", "8080")));
+    }
+
+    #[test]
+    fn protocol_restricts_matches() {
+        let spec = PortSpec::parse(":80/udp").unwrap();
+        assert!(spec.matches(&listener(Protocol::Udp, "::", "80")));
+        assert!(!spec.matches(&listener(Protocol::Tcp, "0.0.0.0", "80")));
+    }
+
+    #[test]
+    fn unix_spec_matches_socket_path() {
+        let spec = PortSpec::parse("/path.sock").unwrap();
+        let mut socket = listener(Protocol::Unix, "/path.sock", "");
+        socket.family = AddressFamily::Unix;
+        assert!(spec.matches(&socket));
+        assert!(!spec.matches(&listener(Protocol::Unix, "/other.sock", "")));
+        assert!(!PortSpec::parse("80").unwrap().matches(&socket));
+    }
+}
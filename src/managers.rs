@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process::{Command as StdCommand, Stdio};
+
+use crate::process_table::{ProcessMeta, ProcessTable, StartTime};
+use crate::{privileged, ProcessInfo};
+
+/// A process manager that restarts what it runs, so killing the listener isn't enough
+#[derive(Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Manager {
+    Pm2,
+    Supervisord,
+    Foreman,
+    Overmind,
+    Honcho,
+}
+
+impl Manager {
+    pub fn name(self) -> &'static str {
+        match self {
+            Manager::Pm2 => "pm2",
+            Manager::Supervisord => "supervisord",
+            Manager::Foreman => "foreman",
+            Manager::Overmind => "overmind",
+            Manager::Honcho => "honcho",
+        }
+    }
+
+    /// Recognizes a manager by its command line. pm2 and foreman rename their process;
+    /// the others run under an interpreter ("python3 /usr/bin/supervisord -c ...") or,
+    /// for overmind, as the tmux server it started.
+    fn detect(meta: &ProcessMeta) -> Option<Manager> {
+        let cmdline = &meta.cmdline;
+        if cmdline.starts_with("PM2 v") {
+            return Some(Manager::Pm2);
+        }
+        if cmdline.starts_with("foreman: master") {
+            return Some(Manager::Foreman);
+        }
+        if meta.comm.starts_with("tmux") && cmdline.contains("overmind") {
+            return Some(Manager::Overmind);
+        }
+
+        let programs: Vec<&str> = cmdline
+            .split_whitespace()
+            .take(2)
+            .map(|word| word.rsplit('/').next().unwrap_or(word))
+            .collect();
+        [Manager::Supervisord, Manager::Foreman, Manager::Overmind, Manager::Honcho]
+            .into_iter()
+            .find(|manager| programs.contains(&manager.name()))
+    }
+}
+
+impl fmt::Display for Manager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A listener run by a process manager, and how to stop it through that manager
+#[derive(Clone, serde::Serialize)]
+pub(crate) struct ManagedApp {
+    pub manager: Manager,
+    /// The app (pm2), program (supervisord) or Procfile entry (foreman, overmind, honcho)
+    pub name: String,
+    pub manager_pid: u32,
+    #[serde(skip)]
+    pub manager_started: Option<StartTime>,
+    #[serde(skip)]
+    pub manager_uid: Option<u32>,
+    #[serde(skip)]
+    pub stop: StopMethod,
+}
+
+impl fmt::Display for ManagedApp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.manager, self.name)
+    }
+}
+
+#[derive(Clone)]
+pub(crate) enum StopMethod {
+    /// The manager's own stop command
+    Command(ManagerCommand),
+    /// Foreman and honcho have no way to stop a single process, and shut everything down
+    /// once any of them exits, so the only clean stop is signalling the manager itself
+    SignalManager,
+}
+
+/// A command such as `pm2 stop api`, with what it needs to find the right daemon
+#[derive(Clone)]
+pub(crate) struct ManagerCommand {
+    args: Vec<String>,
+    env: Vec<(String, String)>,
+    dir: Option<PathBuf>,
+}
+
+impl ManagerCommand {
+    /// Runs the command, through `sudo env` when elevated so the environment survives
+    pub fn run(&self, with_sudo: bool) -> Result<(), String> {
+        let mut command = if with_sudo {
+            let mut command = privileged::sudo_command();
+            command.arg("env");
+            command.args(self.env.iter().map(|(key, value)| format!("{}={}", key, value)));
+            command.args(&self.args);
+            command
+        } else {
+            let mut command = StdCommand::new(&self.args[0]);
+            command.args(&self.args[1..]).envs(self.env.iter().cloned());
+            command
+        };
+        if let Some(dir) = &self.dir {
+            command.current_dir(dir);
+        }
+
+        let output = command.stdin(Stdio::inherit()).output().map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => format!("{} is not installed", self.args[0]),
+            _ => e.to_string(),
+        })?;
+        if output.status.success() {
+            return Ok(());
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Err(if stderr.is_empty() { stdout } else { stderr })
+    }
+}
+
+impl fmt::Display for ManagerCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.args.join(" "))
+    }
+}
+
+/// Looks for a process manager among the ancestors of each row's process. The table holds
+/// the rows' processes; their ancestors are added a generation at a time for all rows at
+/// once, so only the processes above the listeners are read, not the whole system.
+pub(crate) fn find_managed_apps(processes: &mut [ProcessInfo], table: &mut ProcessTable) {
+    // (row, listener PID, the process whose parent comes next). Wrappers (sh -c, npm) may
+    // sit between the manager and the listener; the manager's direct child is the program
+    // it was told to run.
+    let mut walks: Vec<(usize, u32, u32)> = processes
+        .iter()
+        .enumerate()
+        .filter_map(|(index, process)| process.pid.parse().ok().map(|pid| (index, pid, pid)))
+        .collect();
+
+    for _ in 0..16 {
+        if walks.is_empty() {
+            break;
+        }
+        let parent_of = |table: &ProcessTable, child: u32| table.get(child)?.ppid.filter(|&parent| parent > 1);
+        let parents: Vec<u32> = walks.iter().filter_map(|&(_, _, child)| parent_of(table, child)).collect();
+        table.extend(&parents);
+
+        walks.retain_mut(|(index, pid, child)| {
+            let Some((parent, meta)) = parent_of(table, *child).and_then(|parent| Some((parent, table.get(parent)?))) else {
+                return false;
+            };
+            let Some(manager) = Manager::detect(meta) else {
+                *child = parent;
+                return true;
+            };
+            // Managers tell their programs who they are through the environment
+            let env = read_environ(*pid).or_else(|| read_environ(*child)).unwrap_or_default();
+            let fallback_name = table.get(*child).map(|meta| meta.comm.clone()).unwrap_or_default();
+            processes[*index].managed_by = Some(describe_app(manager, parent, meta, env, fallback_name));
+            false
+        });
+    }
+}
+
+fn describe_app(
+    manager: Manager,
+    manager_pid: u32,
+    manager_meta: &ProcessMeta,
+    mut env: HashMap<String, String>,
+    fallback_name: String,
+) -> ManagedApp {
+    let (name, stop) = match manager {
+        Manager::Pm2 => {
+            let name = env.remove("name").unwrap_or(fallback_name);
+            let mut command = manager_command(["pm2", "stop", &name]);
+            // A non-default PM2_HOME means a separate daemon
+            command.env.extend(env.remove_entry("PM2_HOME"));
+            (name, StopMethod::Command(command))
+        }
+        Manager::Supervisord => {
+            let program = env.remove("SUPERVISOR_PROCESS_NAME").unwrap_or(fallback_name);
+            // Programs in a group are addressed as group:program
+            let name = match env.remove("SUPERVISOR_GROUP_NAME") {
+                Some(group) if group != program => format!("{}:{}", group, program),
+                _ => program,
+            };
+            let mut args = vec!["supervisorctl".to_string()];
+            if let Some(url) = env.remove("SUPERVISOR_SERVER_URL") {
+                args.extend(["-s".to_string(), url]);
+            } else if let Some(config) = config_argument(&manager_meta.cmdline) {
+                args.extend(["-c".to_string(), config]);
+            }
+            args.extend(["stop".to_string(), name.clone()]);
+            (name, StopMethod::Command(ManagerCommand { args, env: Vec::new(), dir: None }))
+        }
+        Manager::Overmind => {
+            // Overmind finds its daemon through .overmind.sock in the project directory
+            let name = env.remove("OVERMIND_PROCESS_NAME").or_else(|| env.remove("PS")).unwrap_or(fallback_name);
+            let mut command = manager_command(["overmind", "stop", &name]);
+            command.dir = fs::read_link(format!("/proc/{}/cwd", manager_pid)).ok();
+            command.env.extend(env.remove_entry("OVERMIND_SOCKET"));
+            (name, StopMethod::Command(command))
+        }
+        Manager::Foreman | Manager::Honcho => (env.remove("PS").unwrap_or(fallback_name), StopMethod::SignalManager),
+    };
+
+    ManagedApp {
+        manager,
+        name,
+        manager_pid,
+        manager_started: manager_meta.started,
+        manager_uid: manager_meta.uid,
+        stop,
+    }
+}
+
+fn manager_command<const N: usize>(args: [&str; N]) -> ManagerCommand {
+    ManagerCommand { args: args.iter().map(|arg| arg.to_string()).collect(), env: Vec::new(), dir: None }
+}
+
+/// The value of `-c`/`--configuration` on supervisord's command line
+fn config_argument(cmdline: &str) -> Option<String> {
+    let mut words = cmdline.split_whitespace();
+    while let Some(word) = words.next() {
+        if word == "-c" || word == "--configuration" {
+            return words.next().map(str::to_string);
+        }
+        if let Some(config) = word.strip_prefix("--configuration=") {
+            return Some(config.to_string());
+        }
+    }
+    None
+}
+
+/// The process's environment, readable for our own processes (or all of them as root)
+fn read_environ(pid: u32) -> Option<HashMap<String, String>> {
+    let raw = fs::read(format!("/proc/{}/environ", pid)).ok()?;
+    Some(
+        raw.split(|&byte| byte == 0)
+            .filter_map(|entry| {
+                let entry = String::from_utf8_lossy(entry);
+                let (key, value) = entry.split_once('=')?;
+                Some((key.to_string(), value.to_string()))
+            })
+            .collect(),
+    )
+}
@@ -0,0 +1,248 @@
+use std::io;
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::procfs::{map_socket_inodes_to_pids, resolve_socket_owners, SocketEntry};
+use crate::{ProcessInfo, Protocol};
+
+// From include/uapi/linux/sock_diag.h and inet_diag.h
+const SOCK_DIAG_BY_FAMILY: u16 = 20;
+const TCP_ESTABLISHED: u32 = 1;
+const TCP_LISTEN: u32 = 10;
+// Unconnected UDP sockets report TCP_CLOSE, which is what `ss -ul` lists
+const TCP_CLOSE: u32 = 7;
+// Attribute sent with every IPv6 socket, holding its IPV6_V6ONLY flag
+const INET_DIAG_SKV6ONLY: u16 = 11;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct InetDiagSockId {
+    sport: [u8; 2],
+    dport: [u8; 2],
+    src: [u8; 16],
+    dst: [u8; 16],
+    interface: u32,
+    cookie: [u32; 2],
+}
+
+#[repr(C)]
+struct InetDiagReqV2 {
+    family: u8,
+    protocol: u8,
+    ext: u8,
+    pad: u8,
+    states: u32,
+    id: InetDiagSockId,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct InetDiagMsg {
+    family: u8,
+    state: u8,
+    timer: u8,
+    retrans: u8,
+    id: InetDiagSockId,
+    expires: u32,
+    rqueue: u32,
+    wqueue: u32,
+    uid: u32,
+    inode: u32,
+}
+
+#[repr(C)]
+struct DiagRequest {
+    header: libc::nlmsghdr,
+    body: InetDiagReqV2,
+}
+
+/// Lists listening TCP and UDP sockets through NETLINK_SOCK_DIAG.
+///
+/// Returns `None` if the netlink socket can't be used, so the caller can fall back
+/// to /proc or the command based discovery.
+pub fn scan_listeners() -> Option<Vec<ProcessInfo>> {
+    let socket = DiagSocket::open().ok()?;
+    let mut sockets = Vec::new();
+
+    let queries = [
+        (Protocol::Tcp, libc::IPPROTO_TCP, TCP_LISTEN),
+        (Protocol::Udp, libc::IPPROTO_UDP, TCP_CLOSE),
+    ];
+    for (protocol, ip_protocol, state) in queries {
+        for family in [libc::AF_INET, libc::AF_INET6] {
+            sockets.extend(socket.dump(family as u8, protocol, ip_protocol as u8, 1 << state).ok()?);
+        }
+    }
+
+    Some(resolve_socket_owners(sockets, &map_socket_inodes_to_pids()))
+}
+
+/// Lists established TCP connections through NETLINK_SOCK_DIAG
+pub(crate) fn dump_established() -> Option<Vec<SocketEntry>> {
+    let socket = DiagSocket::open().ok()?;
+    let mut sockets = Vec::new();
+
+    for family in [libc::AF_INET, libc::AF_INET6] {
+        sockets.extend(
+            socket
+                .dump(family as u8, Protocol::Tcp, libc::IPPROTO_TCP as u8, 1 << TCP_ESTABLISHED)
+                .ok()?,
+        );
+    }
+
+    Some(sockets)
+}
+
+struct DiagSocket {
+    fd: libc::c_int,
+}
+
+impl DiagSocket {
+    fn open() -> io::Result<Self> {
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+                libc::NETLINK_SOCK_DIAG,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(DiagSocket { fd })
+    }
+
+    fn dump(&self, family: u8, protocol: Protocol, ip_protocol: u8, states: u32) -> io::Result<Vec<SocketEntry>> {
+        self.send_request(family, ip_protocol, states)?;
+
+        let mut sockets = Vec::new();
+        let mut buffer = vec![0u8; 32 * 1024];
+        loop {
+            let received = unsafe {
+                libc::recv(self.fd, buffer.as_mut_ptr().cast(), buffer.len(), 0)
+            };
+            if received < 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let mut offset = 0;
+            let received = received as usize;
+            while offset + mem::size_of::<libc::nlmsghdr>() <= received {
+                let header: libc::nlmsghdr = read_struct(&buffer[offset..]);
+                let length = header.nlmsg_len as usize;
+                if length < mem::size_of::<libc::nlmsghdr>() || offset + length > received {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "truncated netlink message"));
+                }
+
+                match header.nlmsg_type as libc::c_int {
+                    libc::NLMSG_DONE => return Ok(sockets),
+                    libc::NLMSG_ERROR => {
+                        let payload = &buffer[offset + mem::size_of::<libc::nlmsghdr>()..];
+                        let errno: i32 = read_struct(payload);
+                        return Err(io::Error::from_raw_os_error(-errno));
+                    }
+                    _ => {
+                        let payload = &buffer[offset + mem::size_of::<libc::nlmsghdr>()..offset + length];
+                        if payload.len() >= mem::size_of::<InetDiagMsg>() {
+                            let message: InetDiagMsg = read_struct(payload);
+                            sockets.push(SocketEntry {
+                                protocol,
+                                address: to_ip_address(message.family, message.id.src),
+                                port: u16::from_be_bytes(message.id.sport),
+                                remote_address: to_ip_address(message.family, message.id.dst),
+                                remote_port: u16::from_be_bytes(message.id.dport),
+                                uid: message.uid,
+                                inode: message.inode as u64,
+                                recv_q: message.rqueue,
+                                send_q: message.wqueue,
+                                v6only: find_attribute(&payload[mem::size_of::<InetDiagMsg>()..], INET_DIAG_SKV6ONLY)
+                                    .and_then(|value| value.first())
+                                    .map(|&flag| flag != 0),
+                            });
+                        }
+                    }
+                }
+
+                // Messages are padded to 4 bytes (NLMSG_ALIGN)
+                offset += (length + 3) & !3;
+            }
+        }
+    }
+
+    fn send_request(&self, family: u8, protocol: u8, states: u32) -> io::Result<()> {
+        let request = DiagRequest {
+            header: libc::nlmsghdr {
+                nlmsg_len: mem::size_of::<DiagRequest>() as u32,
+                nlmsg_type: SOCK_DIAG_BY_FAMILY,
+                nlmsg_flags: (libc::NLM_F_REQUEST | libc::NLM_F_DUMP) as u16,
+                nlmsg_seq: 0,
+                nlmsg_pid: 0,
+            },
+            body: InetDiagReqV2 {
+                family,
+                protocol,
+                ext: 0,
+                pad: 0,
+                states,
+                id: unsafe { mem::zeroed() },
+            },
+        };
+
+        let mut address: libc::sockaddr_nl = unsafe { mem::zeroed() };
+        address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+
+        let sent = unsafe {
+            libc::sendto(
+                self.fd,
+                (&request as *const DiagRequest).cast(),
+                mem::size_of::<DiagRequest>(),
+                0,
+                (&address as *const libc::sockaddr_nl).cast(),
+                mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if sent < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for DiagSocket {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
+fn to_ip_address(family: u8, bytes: [u8; 16]) -> IpAddr {
+    if family == libc::AF_INET6 as u8 {
+        IpAddr::V6(Ipv6Addr::from(bytes))
+    } else {
+        IpAddr::V4(Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3]))
+    }
+}
+
+/// The payload of the first rtattr of type `kind` among the ones following a message
+fn find_attribute(mut attributes: &[u8], kind: u16) -> Option<&[u8]> {
+    // struct rtattr { unsigned short rta_len; unsigned short rta_type; }, padded to 4 bytes
+    while attributes.len() >= 4 {
+        let length = u16::from_ne_bytes([attributes[0], attributes[1]]) as usize;
+        let attribute_type = u16::from_ne_bytes([attributes[2], attributes[3]]);
+        if length < 4 || length > attributes.len() {
+            return None;
+        }
+        if attribute_type == kind {
+            return Some(&attributes[4..length]);
+        }
+        attributes = attributes.get((length + 3) & !3..)?;
+    }
+    None
+}
+
+fn read_struct<T: Copy>(bytes: &[u8]) -> T {
+    assert!(bytes.len() >= mem::size_of::<T>());
+    // Netlink buffers carry no alignment guarantees for our structs
+    unsafe { std::ptr::read_unaligned(bytes.as_ptr().cast()) }
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+use crate::procfs::{self, SocketEntry};
+use crate::{get_process_name_by_pid, ProcessInfo, Sighting};
+
+/// A network namespace other than ours, with a way to look inside it
+struct NetNamespace {
+    inode: u64,
+    /// A process living in the namespace, whose /proc/<pid>/net shows its sockets
+    pid: Option<u32>,
+    /// A named namespace from `ip netns add`, which may have no processes at all
+    path: Option<PathBuf>,
+    name: Option<String>,
+}
+
+/// Lists listeners in every network namespace except ours: containers without
+/// published ports, `ip netns` sandboxes, Podman pods and PrivateNetwork= services.
+/// Each row is tagged with the namespace it was found in.
+pub fn scan_other_namespaces() -> Vec<ProcessInfo> {
+    let own = namespace_inode(Path::new("/proc/self/ns/net"));
+    let mut processes = Vec::new();
+    // /proc/<pid>/fd lists the sockets of every namespace, so one walk covers them all
+    let owners = procfs::map_socket_inodes_to_pids();
+
+    for namespace in list_namespaces() {
+        if Some(namespace.inode) == own {
+            continue;
+        }
+
+        let sockets = match (namespace.pid, &namespace.path) {
+            (Some(pid), _) => procfs::read_listening_sockets(&format!("/proc/{}/net", pid)),
+            (None, Some(path)) => read_sockets_in_namespace(path),
+            (None, None) => None,
+        };
+        let Some(sockets) = sockets else {
+            continue;
+        };
+
+        let label = describe_namespace(&namespace);
+        for mut process_info in procfs::resolve_socket_owners(sockets, &owners) {
+            process_info.netns = label.clone();
+            process_info.sources.push(Sighting { source: "proc", pid: process_info.pid.clone(), lookup: false });
+            processes.push(process_info);
+        }
+    }
+
+    processes
+}
+
+fn list_namespaces() -> Vec<NetNamespace> {
+    let mut by_inode: HashMap<u64, NetNamespace> = HashMap::new();
+
+    let mut pids: Vec<u32> = fs::read_dir("/proc")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+        .collect();
+    // The lowest PID is usually the namespace's init (container entrypoint, pod infra)
+    pids.sort_unstable();
+
+    for pid in pids {
+        if let Some(inode) = namespace_inode(Path::new(&format!("/proc/{}/ns/net", pid))) {
+            by_inode.entry(inode).or_insert(NetNamespace { inode, pid: Some(pid), path: None, name: None });
+        }
+    }
+
+    // Named namespaces are bind mounts of the nsfs inode
+    for dir in ["/run/netns", "/var/run/netns"] {
+        for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+            let Ok(metadata) = fs::metadata(entry.path()) else {
+                continue;
+            };
+            let namespace = by_inode.entry(metadata.ino()).or_insert(NetNamespace {
+                inode: metadata.ino(),
+                pid: None,
+                path: None,
+                name: None,
+            });
+            namespace.path.get_or_insert(entry.path());
+            namespace.name.get_or_insert(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+
+    let mut namespaces: Vec<NetNamespace> = by_inode.into_values().collect();
+    namespaces.sort_by_key(|namespace| namespace.inode);
+    namespaces
+}
+
+fn namespace_inode(link: &Path) -> Option<u64> {
+    // Namespace links read as "net:[4026531840]"
+    let target = fs::read_link(link).ok()?;
+    target
+        .to_str()?
+        .strip_prefix("net:[")?
+        .strip_suffix(']')?
+        .parse()
+        .ok()
+}
+
+/// Names a namespace after `ip netns`, the container living in it, or its first process
+fn describe_namespace(namespace: &NetNamespace) -> String {
+    if let Some(name) = &namespace.name {
+        return format!("netns {}", name);
+    }
+
+    if let Some(pid) = namespace.pid {
+        if let Some((runtime, id)) = container_id_from_cgroup(pid) {
+            return format!("{} {}", runtime, &id[..12]);
+        }
+        return format!("pid {} ({})", pid, get_process_name_by_pid(&pid.to_string()));
+    }
+
+    format!("net:[{}]", namespace.inode)
+}
+
+fn container_id_from_cgroup(pid: u32) -> Option<(&'static str, String)> {
+    // e.g. 0::/system.slice/docker-<id>.scope, 0::/docker/<id> or .../libpod-<id>.scope
+    let cgroup = fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    for (marker, runtime) in [("docker-", "docker"), ("docker/", "docker"), ("libpod-", "podman")] {
+        for (index, _) in cgroup.match_indices(marker) {
+            let id: String = cgroup[index + marker.len()..]
+                .chars()
+                .take_while(|c| c.is_ascii_hexdigit())
+                .collect();
+            if id.len() == 64 {
+                return Some((runtime, id));
+            }
+        }
+    }
+    None
+}
+
+/// Reads the socket tables of a namespace nobody lives in by entering it from a
+/// throwaway thread; setns() only affects the calling thread. Needs CAP_SYS_ADMIN.
+fn read_sockets_in_namespace(path: &Path) -> Option<Vec<SocketEntry>> {
+    let file = fs::File::open(path).ok()?;
+    std::thread::spawn(move || {
+        if unsafe { libc::setns(file.as_raw_fd(), libc::CLONE_NEWNET) } != 0 {
+            return None;
+        }
+        procfs::read_listening_sockets("/proc/thread-self/net")
+    })
+    .join()
+    .ok()
+    .flatten()
+}
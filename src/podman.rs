@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::{Command as StdCommand, Stdio};
+use std::time::Duration;
+
+use crate::docker::{self, Container, Engine, EngineError, PortBinding};
+use crate::privileged;
+
+/// The processes holding a Podman container's published ports on the host. conmon keeps
+/// the ports of rootful containers reserved; rootless ones are forwarded by rootlessport,
+/// or by pasta or slirp4netns when they do the forwarding themselves.
+const PORT_FORWARDERS: [&str; 5] = ["conmon", "rootlessport", "pasta", "pasta.avx2", "slirp4netns"];
+
+pub(crate) fn is_port_forwarder(process_name: &str) -> bool {
+    PORT_FORWARDERS.contains(&process_name)
+}
+
+/// The container a conmon process monitors, from its `-c`/`--cid` argument:
+/// "/usr/bin/conmon --api-version 1 -c 5f3c... -u 5f3c... -r /usr/bin/crun -n web ..."
+pub(crate) fn conmon_container_id(command: &str) -> Option<&str> {
+    let mut args = command.split_whitespace();
+    while let Some(arg) = args.next() {
+        if arg == "-c" || arg == "--cid" {
+            return args.next();
+        }
+        if let Some(id) = arg.strip_prefix("--cid=") {
+            return Some(id);
+        }
+    }
+    None
+}
+
+/// The API socket of `uid`'s Podman instance: CONTAINER_HOST for our own, the system
+/// socket for root, and the one in the user's runtime directory otherwise
+pub(crate) fn host(uid: u32) -> String {
+    if uid == privileged::effective_uid() {
+        if let Some(host) = env::var("CONTAINER_HOST").ok().filter(|host| !host.is_empty()) {
+            return host;
+        }
+        if let Some(dir) = env::var("XDG_RUNTIME_DIR").ok().filter(|dir| uid != 0 && !dir.is_empty()) {
+            return format!("unix://{}/podman/podman.sock", dir);
+        }
+    }
+    format!("unix://{}", socket_path(uid))
+}
+
+fn socket_path(uid: u32) -> String {
+    match uid {
+        0 => "/run/podman/podman.sock".to_string(),
+        uid => format!("/run/user/{}/podman/podman.sock", uid),
+    }
+}
+
+/// The Podman instances worth asking for containers: root's and our own, and as root every
+/// user's that has its API socket up. Podman on macOS and Windows runs in a VM, whose
+/// forwarded ports aren't held by any of the processes above.
+pub(crate) fn instances() -> Vec<u32> {
+    if !cfg!(target_os = "linux") {
+        return Vec::new();
+    }
+    let own = privileged::effective_uid();
+    let mut uids = vec![0];
+    if own != 0 {
+        uids.push(own);
+    } else if let Ok(entries) = fs::read_dir("/run/user") {
+        uids.extend(
+            entries
+                .flatten()
+                .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+                .filter(|&uid| uid != 0 && Path::new(&socket_path(uid)).exists()),
+        );
+        uids.sort_unstable();
+    }
+    uids
+}
+
+/// The instance's running containers, from `GET /libpod/containers/json`, or with `cli`
+/// from `podman ps` when the socket is off (podman.socket isn't enabled by default)
+pub(crate) fn list_containers(uid: u32, cli: bool) -> Result<Vec<Container>, EngineError> {
+    let path = "/libpod/containers/json";
+    let body = if cli {
+        api_or_cli(uid, "GET", path, docker::READ_TIMEOUT, &["ps", "--format", "json"])?
+    } else {
+        docker::request(Engine::Podman { uid }, "GET", path, docker::READ_TIMEOUT)?
+    };
+    let containers: Vec<PodmanContainer> = serde_json::from_slice(&body).map_err(|e| EngineError::Unavailable {
+        engine: Engine::Podman { uid },
+        reason: format!("unexpected answer from Podman: {}", e),
+    })?;
+    Ok(containers.into_iter().map(|container| container.into_container(uid)).collect())
+}
+
+/// Stops the container like `podman stop`, or its whole pod like `podman pod stop`:
+/// the pod's infra container holds the ports for all of them
+pub(crate) fn stop(uid: u32, container: &Container) -> Result<(), EngineError> {
+    let result = match &container.pod {
+        Some(pod) => {
+            api_or_cli(uid, "POST", &format!("/libpod/pods/{}/stop", pod), docker::STOP_TIMEOUT, &["pod", "stop", pod])
+        }
+        None => api_or_cli(
+            uid,
+            "POST",
+            &format!("/libpod/containers/{}/stop", container.id),
+            docker::STOP_TIMEOUT,
+            &["stop", &container.id],
+        ),
+    };
+    match result {
+        Err(EngineError::Api { status: 304, .. }) => Ok(()),
+        result => result.map(|_| ()),
+    }
+}
+
+pub(crate) fn remove(uid: u32, container: &Container) -> Result<(), EngineError> {
+    let result = match &container.pod {
+        Some(pod) => api_or_cli(uid, "DELETE", &format!("/libpod/pods/{}", pod), docker::READ_TIMEOUT, &["pod", "rm", pod]),
+        None => api_or_cli(
+            uid,
+            "DELETE",
+            &format!("/libpod/containers/{}", container.id),
+            docker::READ_TIMEOUT,
+            &["rm", &container.id],
+        ),
+    };
+    result.map(|_| ())
+}
+
+/// Asks the instance's API, and when its socket is off falls back on the podman CLI, which
+/// only reaches our own instance
+fn api_or_cli(uid: u32, method: &str, path: &str, timeout: Duration, args: &[&str]) -> Result<Vec<u8>, EngineError> {
+    let engine = Engine::Podman { uid };
+    let error = match docker::request(engine, method, path, timeout) {
+        Err(error @ EngineError::Unavailable { .. }) if uid == privileged::effective_uid() => error,
+        result => return result,
+    };
+
+    let output = match StdCommand::new("podman").args(args).stdin(Stdio::null()).output() {
+        Ok(output) => output,
+        // Without the CLI either, the socket's error says more
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(error),
+        Err(e) => return Err(EngineError::Unavailable { engine, reason: format!("podman: {}", e) }),
+    };
+    if output.status.success() {
+        return Ok(output.stdout);
+    }
+    Err(EngineError::Failed(format!("podman {}: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim())))
+}
+
+/// One entry of the libpod container list, which `podman ps --format json` prints as well
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct PodmanContainer {
+    id: String,
+    #[serde(default)]
+    names: Vec<String>,
+    #[serde(default)]
+    image: String,
+    #[serde(default)]
+    ports: Option<Vec<PodmanPort>>,
+    #[serde(default)]
+    labels: Option<BTreeMap<String, String>>,
+    #[serde(default)]
+    pod: String,
+    #[serde(default)]
+    pod_name: String,
+    #[serde(default)]
+    is_infra: bool,
+}
+
+#[derive(serde::Deserialize)]
+struct PodmanPort {
+    #[serde(default)]
+    host_ip: String,
+    container_port: u16,
+    host_port: u16,
+    /// How many consecutive ports the mapping covers, from 8080-8089:80-89
+    #[serde(default)]
+    range: u16,
+    /// "tcp", "udp", or both as "tcp,udp"
+    protocol: String,
+}
+
+impl PodmanContainer {
+    fn into_container(self, uid: u32) -> Container {
+        let pod = [self.pod_name, self.pod].into_iter().find(|pod| !pod.is_empty());
+        // Every container of a pod lists the pod's ports, but only the infra container
+        // publishes them
+        let ports = if pod.is_some() && !self.is_infra { Vec::new() } else { self.ports.unwrap_or_default() };
+        let ports = ports
+            .into_iter()
+            .flat_map(|port| {
+                let host_ip = Some(port.host_ip).filter(|ip| !ip.is_empty());
+                let protocols: Vec<String> = port.protocol.split(',').map(str::to_string).collect();
+                (0..port.range.max(1)).flat_map(move |offset| {
+                    let host_ip = host_ip.clone();
+                    protocols.clone().into_iter().map(move |protocol| PortBinding {
+                        host_ip: host_ip.clone(),
+                        host_port: port.host_port.checked_add(offset),
+                        container_port: port.container_port.saturating_add(offset),
+                        protocol,
+                    })
+                })
+            })
+            .collect();
+        Container {
+            engine: Engine::Podman { uid },
+            id: self.id,
+            name: self.names.into_iter().next().unwrap_or_default(),
+            image: self.image,
+            // The list only names the networks; port bindings are all that's needed here
+            ips: Vec::new(),
+            ports,
+            labels: self.labels.unwrap_or_default(),
+            pod,
+        }
+    }
+}
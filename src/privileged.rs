@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command as StdCommand, Stdio};
+
+use crate::{create_shared_process_info, get_user_confirmation, procfs, same_address, sources, ProcessInfo, Protocol, Sighting};
+
+/// Looks up the owners of hidden rows with sudo. Nothing but the socket-to-PID mapping
+/// runs privileged: on Linux that's apps-ports itself with the hidden `--socket-owners`, elsewhere
+/// `lsof`. `sudo -n` is tried first, and a password is only asked for on a terminal.
+pub(crate) fn resolve_hidden_with_sudo(processes: &mut [ProcessInfo]) {
+    let hidden = processes.iter().filter(|process| process.is_hidden()).count();
+    if hidden == 0 || is_root() {
+        return;
+    }
+
+    let noun = if hidden == 1 { "socket" } else { "sockets" };
+    let reason = format!("look up the owner of {} hidden {}", hidden, noun);
+    let resolved = if cfg!(target_os = "linux") {
+        resolve_by_inode(processes, &reason)
+    } else {
+        resolve_with_lsof(processes, &reason)
+    };
+
+    // run_with_sudo already explained why when sudo couldn't run at all
+    if resolved.is_some_and(|resolved| resolved < hidden) {
+        eprintln!("⚠ {} of {} hidden {} could not be resolved with sudo", hidden - resolved.unwrap_or(0), hidden, noun);
+    }
+}
+
+fn resolve_by_inode(processes: &mut [ProcessInfo], reason: &str) -> Option<usize> {
+    let owners = socket_owners_with_sudo(reason)?;
+    let mut resolved = 0;
+    for process_info in processes.iter_mut().filter(|process| process.is_hidden()) {
+        let Some(pids) = process_info.inode.and_then(|inode| owners.get(&inode)) else {
+            continue;
+        };
+        let owner = create_shared_process_info(
+            process_info.protocol,
+            process_info.family,
+            process_info.address.clone(),
+            process_info.port.clone(),
+            pids,
+        );
+        adopt_privileged_owner(process_info, owner);
+        resolved += 1;
+    }
+    Some(resolved)
+}
+
+/// Maps every socket inode to the PIDs holding it, as root through `--socket-owners` (Linux)
+pub(crate) fn socket_owners_with_sudo(reason: &str) -> Option<HashMap<u64, Vec<u32>>> {
+    let exe = std::env::current_exe().ok()?.to_string_lossy().to_string();
+    let output = run_with_sudo(&[&exe, "--socket-owners"], reason)?;
+    Some(output.lines().filter_map(parse_owner_line).collect())
+}
+
+fn resolve_with_lsof(processes: &mut [ProcessInfo], reason: &str) -> Option<usize> {
+    let output = run_with_sudo(&["lsof", "-i", "-P", "-n"], reason)?;
+
+    let owners = sources::parse_lsof_output(&output);
+    let mut resolved = 0;
+    for process_info in processes.iter_mut().filter(|process| process.is_hidden() && process.protocol != Protocol::Unix) {
+        let Some(owner) = owners.iter().find(|owner| {
+            owner.protocol == process_info.protocol
+                && owner.port == process_info.port
+                && same_address(&owner.address, &process_info.address)
+        }) else {
+            continue;
+        };
+        let pids: Vec<u32> = owner.holders.iter().filter_map(|holder| holder.pid.parse().ok()).collect();
+        let owner = create_shared_process_info(
+            process_info.protocol,
+            process_info.family,
+            process_info.address.clone(),
+            process_info.port.clone(),
+            &pids,
+        );
+        adopt_privileged_owner(process_info, owner);
+        resolved += 1;
+    }
+    Some(resolved)
+}
+
+fn adopt_privileged_owner(process_info: &mut ProcessInfo, owner: ProcessInfo) {
+    process_info.sources.push(Sighting { source: "sudo", pid: owner.pid.clone(), lookup: true });
+    sources::adopt_owner(process_info, owner);
+}
+
+/// Runs a command with sudo and returns its output. Cached credentials and NOPASSWD
+/// rules work without a prompt; otherwise the user is asked first.
+pub(crate) fn run_with_sudo(args: &[&str], reason: &str) -> Option<String> {
+    let output = match StdCommand::new("sudo").arg("-n").args(args).stderr(Stdio::null()).output() {
+        Ok(output) => output,
+        Err(e) => {
+            eprintln!("✗ Can't run sudo: {}", e);
+            return None;
+        }
+    };
+    if output.status.success() {
+        return Some(String::from_utf8_lossy(&output.stdout).to_string());
+    }
+
+    if !io::stdin().is_terminal() {
+        eprintln!("✗ sudo needs a password to {}, but stdin is not a terminal", reason);
+        return None;
+    }
+
+    // Prompt on stderr so --json output stays parseable
+    eprint!("sudo needs a password to {}. Continue? [y/N]: ", reason);
+    io::stderr().flush().unwrap();
+    if !get_user_confirmation() {
+        return None;
+    }
+
+    let output = StdCommand::new("sudo")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()
+        .ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Prints "inode pid pid ..." for every socket, for the parent process to read.
+/// This is what runs under sudo, see `resolve_hidden_with_sudo`.
+pub fn print_socket_owners() {
+    let mut owners: Vec<(u64, Vec<u32>)> = procfs::map_socket_inodes_to_pids().into_iter().collect();
+    owners.sort_unstable();
+    for (inode, pids) in owners {
+        let pids: Vec<String> = pids.iter().map(|pid| pid.to_string()).collect();
+        println!("{} {}", inode, pids.join(" "));
+    }
+}
+
+fn parse_owner_line(line: &str) -> Option<(u64, Vec<u32>)> {
+    let mut fields = line.split_whitespace();
+    let inode = fields.next()?.parse().ok()?;
+    let pids: Vec<u32> = fields.filter_map(|pid| pid.parse().ok()).collect();
+    (!pids.is_empty()).then_some((inode, pids))
+}
+
+/// `sudo`, ready for the command to run. sudo asks for a password on the terminal if it
+/// needs one; without a terminal (--yes in a script) it has to work without one or fail
+/// right away.
+pub(crate) fn sudo_command() -> StdCommand {
+    let mut command = StdCommand::new("sudo");
+    if !io::stdin().is_terminal() {
+        command.arg("-n");
+    }
+    command
+}
+
+pub(crate) fn is_root() -> bool {
+    effective_uid() == 0
+}
+
+#[cfg(unix)]
+pub(crate) fn effective_uid() -> u32 {
+    // SAFETY: geteuid has no preconditions and can't fail
+    unsafe { libc::geteuid() }
+}
+
+#[cfg(not(unix))]
+pub(crate) fn effective_uid() -> u32 {
+    u32::MAX
+}
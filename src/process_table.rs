@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command as StdCommand;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// What we show about a process, gathered for every PID of interest in one pass
+pub(crate) struct ProcessMeta {
+    pub ppid: Option<u32>,
+    /// Process group ID
+    pub pgid: Option<u32>,
+    /// Session ID, only known from /proc
+    pub sid: Option<u32>,
+    /// Short executable name, as in /proc/<pid>/comm
+    pub comm: String,
+    pub cmdline: String,
+    /// Effective user ID
+    pub uid: Option<u32>,
+    /// Seconds since the Unix epoch
+    pub start_time: Option<u64>,
+    /// The start time as precisely as it was read, to tell the process apart from a later
+    /// one reusing its PID
+    pub started: Option<StartTime>,
+}
+
+/// When a process started, in the unit it was read in
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum StartTime {
+    /// Clock ticks after boot, from /proc/<pid>/stat: exact, no two processes with the
+    /// same PID share it
+    Ticks(u64),
+    /// Seconds since the Unix epoch, from ps's elapsed time, which is whole seconds
+    Seconds(u64),
+}
+
+impl StartTime {
+    /// Whether a process seen starting at `self` may be the one seen at `other`
+    pub fn matches(self, other: StartTime) -> bool {
+        match (self, other) {
+            (StartTime::Ticks(a), StartTime::Ticks(b)) => a == b,
+            // Two readings of the elapsed time can round to start times a second apart
+            (StartTime::Seconds(a), StartTime::Seconds(b)) => a.abs_diff(b) <= 1,
+            // Not comparable; only happens if /proc came or went between the readings
+            _ => true,
+        }
+    }
+}
+
+pub(crate) struct ProcessTable {
+    processes: HashMap<u32, ProcessMeta>,
+}
+
+impl ProcessTable {
+    /// Looks up every PID in `pids` at once: from /proc where it exists, otherwise with a
+    /// single `ps` invocation. PIDs that have already exited are left out.
+    pub fn load(pids: &[u32]) -> ProcessTable {
+        let mut pids = pids.to_vec();
+        pids.sort_unstable();
+        pids.dedup();
+
+        let processes = if pids.is_empty() {
+            HashMap::new()
+        } else if Path::new("/proc/self/stat").exists() {
+            read_proc(&pids)
+        } else {
+            read_ps(Some(&pids))
+        };
+        ProcessTable { processes }
+    }
+
+    /// Looks up every process on the system, for walking parents and children
+    pub fn load_all() -> ProcessTable {
+        let processes = if Path::new("/proc/self/stat").exists() {
+            let pids: Vec<u32> = fs::read_dir("/proc")
+                .map(|entries| {
+                    entries
+                        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+                        .collect()
+                })
+                .unwrap_or_default();
+            read_proc(&pids)
+        } else {
+            read_ps(None)
+        };
+        ProcessTable { processes }
+    }
+
+    /// Adds the processes of `pids` that aren't in the table yet, looked up in one go
+    pub fn extend(&mut self, pids: &[u32]) {
+        let missing: Vec<u32> = pids.iter().copied().filter(|pid| !self.processes.contains_key(pid)).collect();
+        self.processes.extend(ProcessTable::load(&missing).processes);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &ProcessMeta)> {
+        self.processes.iter().map(|(&pid, meta)| (pid, meta))
+    }
+
+    pub fn get(&self, pid: u32) -> Option<&ProcessMeta> {
+        self.processes.get(&pid)
+    }
+}
+
+fn read_proc(pids: &[u32]) -> HashMap<u32, ProcessMeta> {
+    let boot_time = read_boot_time();
+    let ticks_per_second = clock_ticks_per_second();
+    pids.iter()
+        .filter_map(|&pid| Some((pid, read_proc_process(pid, boot_time, ticks_per_second)?)))
+        .collect()
+}
+
+fn read_proc_process(pid: u32, boot_time: Option<u64>, ticks_per_second: u64) -> Option<ProcessMeta> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The command name is parenthesized and may itself contain spaces or parentheses
+    let (head, fields) = stat.rsplit_once(')')?;
+    let comm = head.split_once('(')?.1.to_string();
+    // Counting from the state (field 3): ppid is field 4, pgrp 5, session 6, starttime 22
+    let fields: Vec<&str> = fields.split_whitespace().collect();
+    let ppid = fields.get(1).and_then(|ppid| ppid.parse().ok());
+    let pgid = fields.get(2).and_then(|pgid| pgid.parse().ok());
+    let sid = fields.get(3).and_then(|sid| sid.parse().ok());
+    let start_ticks = fields.get(19).and_then(|ticks| ticks.parse::<u64>().ok());
+    let start_time = start_ticks.zip(boot_time).map(|(ticks, boot_time)| boot_time + ticks / ticks_per_second);
+    let started = start_ticks.map(StartTime::Ticks);
+
+    let cmdline = fs::read(format!("/proc/{}/cmdline", pid))
+        .map(|raw| {
+            raw.split(|&byte| byte == 0)
+                .filter(|arg| !arg.is_empty())
+                .map(String::from_utf8_lossy)
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .unwrap_or_default();
+    // Kernel threads have no command line; show them like ps does
+    let cmdline = if cmdline.is_empty() { format!("[{}]", comm) } else { cmdline };
+    // Arguments can contain newlines (python -c, sh -c), which would break the table
+    let cmdline = cmdline.chars().map(|c| if c.is_control() { '?' } else { c }).collect();
+
+    let uid = fs::read_to_string(format!("/proc/{}/status", pid)).ok().and_then(|status| {
+        // Uid: real, effective, saved, filesystem
+        status.lines().find_map(|line| line.strip_prefix("Uid:"))?.split_whitespace().nth(1)?.parse().ok()
+    });
+
+    Some(ProcessMeta { ppid, pgid, sid, comm, cmdline, uid, start_time, started })
+}
+
+fn read_boot_time() -> Option<u64> {
+    let stat = fs::read_to_string("/proc/stat").ok()?;
+    stat.lines().find_map(|line| line.strip_prefix("btime "))?.trim().parse().ok()
+}
+
+#[cfg(unix)]
+fn clock_ticks_per_second() -> u64 {
+    // SAFETY: sysconf has no preconditions
+    match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+        ticks if ticks > 0 => ticks as u64,
+        _ => 100,
+    }
+}
+
+#[cfg(not(unix))]
+fn clock_ticks_per_second() -> u64 {
+    100
+}
+
+/// Runs ps for `pids`, or for every process with `None`
+fn read_ps(pids: Option<&[u32]>) -> HashMap<u32, ProcessMeta> {
+    let mut command = StdCommand::new("ps");
+    // args goes last because it contains spaces; comm can too, so it's derived from args.
+    // The session ID is left out: macOS ps only shows the session's kernel address.
+    command.args(["-ww", "-o", "pid=,ppid=,pgid=,uid=,etime=,args="]);
+    match pids {
+        Some(pids) => {
+            let pid_list: Vec<String> = pids.iter().map(|pid| pid.to_string()).collect();
+            command.args(["-p", &pid_list.join(",")]);
+        }
+        None => {
+            command.arg("-A");
+        }
+    }
+    let Ok(output) = command.output() else {
+        return HashMap::new();
+    };
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|now| now.as_secs()).ok();
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| parse_ps_line(line, now))
+        .collect()
+}
+
+fn parse_ps_line(line: &str, now: Option<u64>) -> Option<(u32, ProcessMeta)> {
+    let mut fields = line.split_whitespace();
+    let pid = fields.next()?.parse().ok()?;
+    let ppid = fields.next()?.parse().ok();
+    let pgid = fields.next()?.parse().ok();
+    let uid = fields.next()?.parse().ok();
+    let elapsed = parse_elapsed(fields.next()?);
+    let cmdline = fields.collect::<Vec<_>>().join(" ");
+    let comm = cmdline
+        .split_whitespace()
+        .next()
+        .map(|program| program.rsplit('/').next().unwrap_or(program))
+        .unwrap_or_default()
+        .to_string();
+    let start_time = now.zip(elapsed).map(|(now, elapsed)| now.saturating_sub(elapsed));
+
+    let started = start_time.map(StartTime::Seconds);
+
+    Some((pid, ProcessMeta { ppid, pgid, sid: None, comm, cmdline, uid, start_time, started }))
+}
+
+/// Parses ps's elapsed time, "[[dd-]hh:]mm:ss", into seconds
+fn parse_elapsed(elapsed: &str) -> Option<u64> {
+    let (days, clock) = match elapsed.split_once('-') {
+        Some((days, clock)) => (days.parse::<u64>().ok()?, clock),
+        None => (0, elapsed),
+    };
+    let seconds = clock
+        .split(':')
+        .try_fold(0u64, |total, part| Some(total * 60 + part.parse::<u64>().ok()?))?;
+    Some(days * 86_400 + seconds)
+}
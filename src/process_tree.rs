@@ -0,0 +1,185 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::process_table::{ProcessMeta, ProcessTable};
+use crate::truncate_string;
+
+/// Shells that a user might have typed the command into
+const SHELLS: [&str; 9] = ["sh", "bash", "zsh", "fish", "dash", "ksh", "tcsh", "csh", "nu"];
+
+/// Parents the climb to the launcher never goes past: they start things for the user
+/// but aren't part of what the user started
+const SESSION_MANAGERS: [&str; 10] = [
+    "systemd", "launchd", "init", "tmux", "screen", "sshd", "login", "supervisord", "containerd-shim", "conmon",
+];
+
+/// Every process on the system, linked from parents to children
+pub(crate) struct ProcessTree {
+    table: ProcessTable,
+    children: HashMap<u32, Vec<u32>>,
+}
+
+impl ProcessTree {
+    pub fn load() -> ProcessTree {
+        let table = ProcessTable::load_all();
+        let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+        for (pid, meta) in table.iter() {
+            if let Some(ppid) = meta.ppid.filter(|&ppid| ppid != pid) {
+                children.entry(ppid).or_default().push(pid);
+            }
+        }
+        for pids in children.values_mut() {
+            pids.sort_unstable();
+        }
+        ProcessTree { table, children }
+    }
+
+    pub fn get(&self, pid: u32) -> Option<&ProcessMeta> {
+        self.table.get(pid)
+    }
+
+    /// `pid` and everything below it, parents before their children
+    pub fn subtree(&self, pid: u32) -> Vec<u32> {
+        let mut pids = Vec::new();
+        let mut stack = vec![pid];
+        while let Some(pid) = stack.pop() {
+            if pids.contains(&pid) {
+                continue;
+            }
+            pids.push(pid);
+            if let Some(children) = self.children.get(&pid) {
+                stack.extend(children.iter().rev());
+            }
+        }
+        pids
+    }
+
+    /// Members of a process group, parents before their children
+    pub fn group(&self, pgid: u32) -> Vec<u32> {
+        let members: HashSet<u32> = self
+            .table
+            .iter()
+            .filter(|(_, meta)| meta.pgid == Some(pgid))
+            .map(|(pid, _)| pid)
+            .collect();
+        self.roots(&members).into_iter().flat_map(|root| self.subtree_within(root, &members)).collect()
+    }
+
+    /// `pid`, its parent, and so on up to init
+    pub fn ancestors(&self, pid: u32) -> Vec<u32> {
+        let mut pids = vec![pid];
+        let mut current = pid;
+        while let Some(parent) = self.get(current).and_then(|meta| meta.ppid) {
+            if parent == 0 || pids.contains(&parent) {
+                break;
+            }
+            pids.push(parent);
+            current = parent;
+        }
+        pids
+    }
+
+    /// The topmost ancestor of `pid` that was started along with it: climbs through
+    /// wrappers like `npm run`, `sh -c` or `cargo watch`, and stops below init, session
+    /// leaders, interactive shells, session managers, other users' processes, and
+    /// anything in `stop_at`
+    pub fn launcher(&self, pid: u32, stop_at: &[u32]) -> u32 {
+        let Some(meta) = self.get(pid) else {
+            return pid;
+        };
+
+        let mut current = pid;
+        while let Some(parent) = self.get(current).and_then(|meta| meta.ppid) {
+            let Some(parent_meta) = self.get(parent) else {
+                break;
+            };
+            if parent <= 1
+                || stop_at.contains(&parent)
+                || parent_meta.uid != meta.uid
+                || parent_meta.sid == Some(parent)
+                || is_interactive_shell(parent_meta)
+                || SESSION_MANAGERS.iter().any(|name| parent_meta.comm.starts_with(name))
+            {
+                break;
+            }
+            current = parent;
+        }
+        current
+    }
+
+    /// Draws `pids` as a tree, one line per process, marking those in `marked` with `mark`
+    pub fn render(&self, pids: &[u32], marked: &[u32], mark: &str) -> Vec<String> {
+        let members: HashSet<u32> = pids.iter().copied().collect();
+        let mut lines = Vec::new();
+        for root in self.roots(&members) {
+            self.render_node(root, &members, "", None, marked, mark, &mut lines);
+        }
+        lines
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render_node(
+        &self,
+        pid: u32,
+        members: &HashSet<u32>,
+        prefix: &str,
+        last: Option<bool>,
+        marked: &[u32],
+        mark: &str,
+        lines: &mut Vec<String>,
+    ) {
+        let command = self.get(pid).map(|meta| truncate_string(&meta.cmdline, 80)).unwrap_or_default();
+        let branch = match last {
+            None => "",
+            Some(true) => "└─ ",
+            Some(false) => "├─ ",
+        };
+        let suffix = if marked.contains(&pid) { format!("  ← {}", mark) } else { String::new() };
+        lines.push(format!("{}{}{} {}{}", prefix, branch, pid, command, suffix));
+
+        let children: Vec<u32> = self.children_within(pid, members);
+        let prefix = match last {
+            None => prefix.to_string(),
+            Some(true) => format!("{}   ", prefix),
+            Some(false) => format!("{}│  ", prefix),
+        };
+        for (index, &child) in children.iter().enumerate() {
+            self.render_node(child, members, &prefix, Some(index + 1 == children.len()), marked, mark, lines);
+        }
+    }
+
+    fn children_within(&self, pid: u32, members: &HashSet<u32>) -> Vec<u32> {
+        self.children
+            .get(&pid)
+            .map(|children| children.iter().copied().filter(|child| members.contains(child)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Like `subtree`, but only following children that are members
+    fn subtree_within(&self, pid: u32, members: &HashSet<u32>) -> Vec<u32> {
+        let mut pids = Vec::new();
+        let mut stack = vec![pid];
+        while let Some(pid) = stack.pop() {
+            pids.push(pid);
+            stack.extend(self.children_within(pid, members).into_iter().rev());
+        }
+        pids
+    }
+
+    /// Members whose parent isn't a member
+    fn roots(&self, members: &HashSet<u32>) -> Vec<u32> {
+        let mut roots: Vec<u32> = members
+            .iter()
+            .copied()
+            .filter(|&pid| !self.get(pid).and_then(|meta| meta.ppid).is_some_and(|ppid| members.contains(&ppid)))
+            .collect();
+        roots.sort_unstable();
+        roots
+    }
+}
+
+/// A shell the user types into, as opposed to `sh -c` or a script run by a wrapper:
+/// it has nothing to run but options like `-l` or `-i`
+fn is_interactive_shell(meta: &ProcessMeta) -> bool {
+    let name = meta.comm.trim_start_matches('-');
+    SHELLS.contains(&name) && meta.cmdline.split_whitespace().skip(1).all(|arg| arg.starts_with('-') && arg != "-c")
+}
@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::{
+    create_shared_process_info, hidden_process_info, AddressFamily, ProcessInfo, Protocol,
+};
+
+// From include/net/tcp_states.h; unbound-peer UDP sockets sit in TCP_CLOSE
+const TCP_ESTABLISHED: &str = "01";
+const TCP_TIME_WAIT: &str = "06";
+const TCP_LISTEN: &str = "0A";
+const TCP_CLOSE: &str = "07";
+
+// From include/linux/net.h: __SO_ACCEPTCON marks listening Unix sockets
+const SO_ACCEPTCON: u32 = 0x10000;
+const SOCK_DGRAM: &str = "0002";
+const SS_UNCONNECTED: &str = "01";
+
+/// A kernel socket as reported by /proc/net or sock_diag, before process lookup
+pub(crate) struct SocketEntry {
+    pub protocol: Protocol,
+    pub address: IpAddr,
+    pub port: u16,
+    pub remote_address: IpAddr,
+    pub remote_port: u16,
+    pub uid: u32,
+    pub inode: u64,
+    pub recv_q: u32,
+    pub send_q: u32,
+    /// Whether an IPv6 socket has IPV6_V6ONLY set, when the source reports it (netlink does,
+    /// /proc/net doesn't)
+    pub v6only: Option<bool>,
+}
+
+/// Lists listening TCP and unconnected UDP sockets by reading /proc/net/{tcp,udp}{,6}
+/// and mapping socket inodes back to their owning processes through /proc/<pid>/fd.
+///
+/// Returns `None` when the socket tables can't be read (e.g. not on Linux), so the
+/// caller can fall back to the ss/netstat/lsof based discovery.
+pub fn scan_listeners() -> Option<Vec<ProcessInfo>> {
+    let sockets = read_listening_sockets("/proc/net")?;
+    Some(resolve_socket_owners(sockets, &map_socket_inodes_to_pids()))
+}
+
+/// Reads listening sockets from the tables in `net_dir`. /proc/<pid>/net shows the
+/// tables of that process's network namespace, /proc/net those of our own.
+pub(crate) fn read_listening_sockets(net_dir: &str) -> Option<Vec<SocketEntry>> {
+    read_socket_tables(net_dir, &[
+        ("tcp", Protocol::Tcp, TCP_LISTEN),
+        ("tcp6", Protocol::Tcp, TCP_LISTEN),
+        ("udp", Protocol::Udp, TCP_CLOSE),
+        ("udp6", Protocol::Udp, TCP_CLOSE),
+    ])
+}
+
+/// Reads established TCP connections from /proc/net/tcp and /proc/net/tcp6
+pub(crate) fn read_established_sockets() -> Option<Vec<SocketEntry>> {
+    read_socket_tables("/proc/net", &[
+        ("tcp", Protocol::Tcp, TCP_ESTABLISHED),
+        ("tcp6", Protocol::Tcp, TCP_ESTABLISHED),
+    ])
+}
+
+/// Reads TCP connections in TIME_WAIT, which keep a port from being bound again
+/// for a while by servers that don't set SO_REUSEADDR
+pub(crate) fn read_time_wait_sockets() -> Option<Vec<SocketEntry>> {
+    read_socket_tables("/proc/net", &[
+        ("tcp", Protocol::Tcp, TCP_TIME_WAIT),
+        ("tcp6", Protocol::Tcp, TCP_TIME_WAIT),
+    ])
+}
+
+fn read_socket_tables(net_dir: &str, tables: &[(&str, Protocol, &str)]) -> Option<Vec<SocketEntry>> {
+    let mut sockets = Vec::new();
+    let mut readable = false;
+
+    for &(table, protocol, state) in tables {
+        if let Ok(contents) = fs::read_to_string(format!("{}/{}", net_dir, table)) {
+            readable = true;
+            sockets.extend(contents.lines().skip(1).filter_map(|line| parse_socket_line(protocol, state, line)));
+        }
+    }
+
+    readable.then_some(sockets)
+}
+
+/// Turns raw sockets into rows, looking up the owning process of each inode in `owners`,
+/// from `map_socket_inodes_to_pids`. Sockets whose owner isn't visible become hidden rows.
+pub(crate) fn resolve_socket_owners(sockets: Vec<SocketEntry>, owners: &HashMap<u64, Vec<u32>>) -> Vec<ProcessInfo> {
+    sockets
+        .into_iter()
+        .map(|socket| {
+            let port = socket.port.to_string();
+            let address = socket.address.to_string();
+            let family = match socket.address {
+                IpAddr::V4(_) => AddressFamily::Ipv4,
+                IpAddr::V6(_) => AddressFamily::Ipv6,
+            };
+            let mut process_info = match owners.get(&socket.inode) {
+                Some(pids) => create_shared_process_info(socket.protocol, family, address, port, pids),
+                // Sockets owned by other users can't be mapped without privileges
+                None => hidden_process_info(socket.protocol, family, address, port),
+            };
+            process_info.uid = Some(socket.uid);
+            process_info.inode = Some(socket.inode);
+            process_info.recv_q = Some(socket.recv_q);
+            process_info.send_q = Some(socket.send_q);
+            process_info.v6only = socket.v6only;
+            process_info
+        })
+        .collect()
+}
+
+/// Lists listening Unix domain sockets from /proc/net/unix, plus bound datagram
+/// sockets such as /dev/log which receive without listening.
+///
+/// Returns `None` when /proc/net/unix can't be read.
+pub fn scan_unix_listeners() -> Option<Vec<ProcessInfo>> {
+    let contents = fs::read_to_string("/proc/net/unix").ok()?;
+    let sockets: Vec<(String, u64)> = contents.lines().skip(1).filter_map(parse_unix_line).collect();
+
+    let owners = map_socket_inodes_to_pids();
+    let processes = sockets
+        .into_iter()
+        .map(|(path, inode)| {
+            let mut process_info = match owners.get(&inode) {
+                Some(pids) => create_shared_process_info(Protocol::Unix, AddressFamily::Unix, path, String::new(), pids),
+                None => hidden_process_info(Protocol::Unix, AddressFamily::Unix, path, String::new()),
+            };
+            process_info.inode = Some(inode);
+            process_info
+        })
+        .collect();
+
+    Some(processes)
+}
+
+fn parse_unix_line(line: &str) -> Option<(String, u64)> {
+    // Format: Num RefCount Protocol Flags Type St Inode Path
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() < 8 {
+        // Unbound sockets have no path and can't be connected to
+        return None;
+    }
+
+    let flags = u32::from_str_radix(parts[3], 16).ok()?;
+    let listening = flags & SO_ACCEPTCON != 0 || (parts[4] == SOCK_DGRAM && parts[5] == SS_UNCONNECTED);
+    if !listening {
+        return None;
+    }
+
+    let inode = parts[6].parse::<u64>().ok()?;
+    // Paths may contain spaces; abstract names are printed with a leading '@'
+    let path = parts[7..].join(" ");
+    Some((path, inode))
+}
+
+fn parse_socket_line(protocol: Protocol, listening_state: &str, line: &str) -> Option<SocketEntry> {
+    // Format: sl local_address rem_address st tx_queue:rx_queue tr:tm->when retrnsmt uid timeout inode ...
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() < 10 || parts[3] != listening_state {
+        return None;
+    }
+
+    // local_address is "<hex ip>:<hex port>"
+    let (address_hex, port_hex) = parts[1].rsplit_once(':')?;
+    let address = parse_hex_address(address_hex)?;
+    let port = u16::from_str_radix(port_hex, 16).ok()?;
+    let (remote_address_hex, remote_port_hex) = parts[2].rsplit_once(':')?;
+    let remote_address = parse_hex_address(remote_address_hex)?;
+    let remote_port = u16::from_str_radix(remote_port_hex, 16).ok()?;
+    let (tx_queue, rx_queue) = parts[4].split_once(':')?;
+    let uid = parts[7].parse::<u32>().ok()?;
+    let inode = parts[9].parse::<u64>().ok()?;
+
+    Some(SocketEntry {
+        protocol,
+        address,
+        port,
+        remote_address,
+        remote_port,
+        uid,
+        inode,
+        recv_q: u32::from_str_radix(rx_queue, 16).ok()?,
+        send_q: u32::from_str_radix(tx_queue, 16).ok()?,
+        v6only: None,
+    })
+}
+
+fn parse_hex_address(hex: &str) -> Option<IpAddr> {
+    // The kernel prints each 32-bit word of the address in host byte order
+    let mut bytes = Vec::with_capacity(16);
+    for chunk in hex.as_bytes().chunks(8) {
+        let word = u32::from_str_radix(std::str::from_utf8(chunk).ok()?, 16).ok()?;
+        bytes.extend_from_slice(&word.to_ne_bytes());
+    }
+
+    match bytes.len() {
+        4 => Some(IpAddr::V4(Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3]))),
+        16 => Some(IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(bytes).ok()?))),
+        _ => None,
+    }
+}
+
+/// Maps each socket inode to every process holding it, in PID order
+pub(crate) fn map_socket_inodes_to_pids() -> HashMap<u64, Vec<u32>> {
+    let mut owners = HashMap::new();
+
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return owners;
+    };
+
+    for entry in entries.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else {
+            continue;
+        };
+
+        // Unreadable fd directories belong to other users; skip them silently
+        let Ok(fds) = fs::read_dir(entry.path().join("fd")) else {
+            continue;
+        };
+
+        for fd in fds.flatten() {
+            if let Some(inode) = fs::read_link(fd.path())
+                .ok()
+                .and_then(|target| parse_socket_link(&target.to_string_lossy()))
+            {
+                let pids: &mut Vec<u32> = owners.entry(inode).or_default();
+                // A process may hold the same socket through several fds
+                if !pids.contains(&pid) {
+                    pids.push(pid);
+                }
+            }
+        }
+    }
+
+    owners
+}
+
+/// Whether the process has a file descriptor for the socket with this inode
+pub(crate) fn holds_socket(pid: u32, inode: u64) -> bool {
+    let Ok(fds) = fs::read_dir(format!("/proc/{}/fd", pid)) else {
+        return false;
+    };
+    fds.flatten().any(|fd| {
+        fs::read_link(fd.path()).ok().and_then(|target| parse_socket_link(&target.to_string_lossy())) == Some(inode)
+    })
+}
+
+fn parse_socket_link(target: &str) -> Option<u64> {
+    // Socket fds link to "socket:[<inode>]"
+    target
+        .strip_prefix("socket:[")?
+        .strip_suffix(']')?
+        .parse()
+        .ok()
+}
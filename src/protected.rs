@@ -0,0 +1,235 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::managers::ManagedApp;
+use crate::process_table::ProcessTable;
+use crate::systemd::{self, SystemdUnit};
+use crate::ProcessInfo;
+
+/// Processes that keep the machine reachable or running. Killing one by accident because
+/// it happens to listen on the port you asked about (sshd on 22, systemd-resolved on 53)
+/// can lock you out.
+const BUILT_IN: [&str; 29] = [
+    "init",
+    "systemd",
+    "systemd-resolved",
+    "systemd-networkd",
+    "systemd-journald",
+    "systemd-logind",
+    "launchd",
+    "sshd",
+    "dbus-daemon",
+    "NetworkManager",
+    "wpa_supplicant",
+    "dhclient",
+    "chronyd",
+    "dockerd",
+    "containerd",
+    // Docker's port forwarding; stop the container with --kill-docker-container instead
+    "docker-proxy",
+    // Podman's, rootful and rootless; stopped with their container the same way
+    "conmon",
+    "rootlessport",
+    "pasta",
+    "pasta.avx2",
+    "slirp4netns",
+    "kubelet",
+    "tailscaled",
+    "cupsd",
+    "mDNSResponder",
+    // Units whose names differ from their processes'; sshd and dbus-daemon cover the rest
+    "ssh.service",
+    "ssh.socket",
+    "dbus.service",
+    "dbus.socket",
+];
+
+/// What `-k` refuses to kill without `--force`: PID 1, the built-in names above adjusted by
+/// the user's config file, and the processes apps-ports itself runs under (the shell, the
+/// terminal, the ssh session, the service it runs in)
+pub(crate) struct ProtectedList {
+    names: Vec<String>,
+    own_ancestors: Vec<u32>,
+    own_unit: Option<SystemdUnit>,
+}
+
+impl ProtectedList {
+    pub fn load() -> ProtectedList {
+        let mut names: Vec<String> = BUILT_IN.iter().map(|name| name.to_string()).collect();
+        if let Some(config) = config_path().and_then(|path| fs::read_to_string(path).ok()) {
+            apply_config(&mut names, &config);
+        }
+        ProtectedList { names, own_ancestors: own_ancestors(), own_unit: systemd::unit_for_pid(std::process::id()) }
+    }
+
+    /// Why the process is protected, if it is
+    pub fn reason(&self, pid: u32, process_name: &str) -> Option<String> {
+        if pid == 1 {
+            return Some("PID 1 is the init process".to_string());
+        }
+        if pid == std::process::id() {
+            return Some("it is apps-ports itself".to_string());
+        }
+        if self.own_ancestors.contains(&pid) {
+            return Some("apps-ports is running under it".to_string());
+        }
+        self.protected_process(process_name).map(|name| format!("{} is on the protected list", name))
+    }
+
+    /// Why a row is protected: the first of its holders that is
+    pub fn reason_for_row(&self, process: &ProcessInfo) -> Option<String> {
+        process.holders.iter().find_map(|holder| {
+            let pid = holder.pid.parse().ok()?;
+            let name = if holder.process_name.is_empty() { &process.process_name } else { &holder.process_name };
+            self.reason(pid, name)
+        })
+    }
+
+    /// Why a container is protected: the list also matches image names, so that `postgres`
+    /// covers `postgres:16` and `docker.io/library/postgres`
+    pub fn reason_for_image(&self, image: &str) -> Option<String> {
+        let name = image_name(image);
+        (!name.is_empty() && self.contains(name)).then(|| format!("image {} is on the protected list", name))
+    }
+
+    /// Why stopping a systemd unit is protected: the list matches unit names with or
+    /// without the suffix, so `sshd` covers `sshd.service`. The unit's processes count too,
+    /// except the manager (PID 1 or `systemd --user`), which holds the sockets of socket
+    /// units but isn't stopped with them.
+    pub fn reason_for_unit(&self, unit: &SystemdUnit, process: &ProcessInfo) -> Option<String> {
+        // A CI job running apps-ports would stop its own runner
+        if self.own_unit.as_ref().is_some_and(|own| own.name == unit.name && own.user == unit.user) {
+            return Some(format!("apps-ports is running in {}", unit));
+        }
+        let protected_unit = unit.units_to_stop().into_iter().find(|name| {
+            let base = name.rsplit_once('.').map_or(*name, |(base, _)| base);
+            self.contains(name) || self.contains(base)
+        });
+        if let Some(name) = protected_unit {
+            return Some(format!("{} is on the protected list", name));
+        }
+        process.holders.iter().filter(|holder| holder.pid != "1" && holder.process_name != "systemd").find_map(|holder| {
+            let pid = holder.pid.parse().ok()?;
+            self.reason(pid, &holder.process_name)
+        })
+    }
+
+    /// Why stopping a managed app is protected: the list matches app and program names as
+    /// well as the processes they run
+    pub fn reason_for_app(&self, app: &ManagedApp, process: &ProcessInfo) -> Option<String> {
+        if self.contains(&app.name) {
+            return Some(format!("{} is on the protected list", app.name));
+        }
+        self.reason_for_row(process)
+    }
+
+    /// Rows for the kill confirmation table, with protected processes marked
+    pub fn mark(&self, processes: &[ProcessInfo]) -> Vec<ProcessInfo> {
+        processes
+            .iter()
+            .map(|process| {
+                let mut process = process.clone();
+                if self.reason_for_row(&process).is_some() {
+                    process.process_name = format!("{} (protected)", process.process_name);
+                }
+                process
+            })
+            .collect()
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.names.iter().any(|protected| protected == name)
+    }
+
+    /// The protected name a process goes by. Process names are the kernel's comm, cut to
+    /// 15 bytes, so "systemd-resolved" runs as "systemd-resolve".
+    fn protected_process(&self, comm: &str) -> Option<&str> {
+        self.names
+            .iter()
+            .find(|protected| {
+                *protected == comm
+                    || (comm.len() == COMM_LEN && protected.as_bytes().get(..COMM_LEN) == Some(comm.as_bytes()))
+            })
+            .map(String::as_str)
+    }
+}
+
+/// How much of a process name the kernel keeps (TASK_COMM_LEN, less the NUL)
+const COMM_LEN: usize = 15;
+
+/// `$XDG_CONFIG_HOME/apps-ports/protected`, or `~/.config/apps-ports/protected`
+fn config_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("apps-ports").join("protected"))
+}
+
+/// One process or image name per line adds it to the list, `!name` takes a built-in one
+/// off it, and `#` starts a comment
+fn apply_config(names: &mut Vec<String>, config: &str) {
+    for line in config.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        match line.strip_prefix('!') {
+            Some(name) => names.retain(|protected| protected != name.trim()),
+            None if !names.iter().any(|protected| protected == line) => names.push(line.to_string()),
+            None => {}
+        }
+    }
+}
+
+/// The processes apps-ports runs under, from its parent up to init
+fn own_ancestors() -> Vec<u32> {
+    let mut ancestors = Vec::new();
+    let mut current = std::process::id();
+    while let Some(parent) = ProcessTable::load(&[current]).get(current).and_then(|meta| meta.ppid) {
+        if parent <= 1 || ancestors.contains(&parent) {
+            break;
+        }
+        ancestors.push(parent);
+        current = parent;
+    }
+    ancestors
+}
+
+/// "registry:5000/library/postgres:16@sha256:..." -> "postgres"
+fn image_name(image: &str) -> &str {
+    let image = image.split('@').next().unwrap_or(image);
+    let name = image.rsplit('/').next().unwrap_or(image);
+    name.split(':').next().unwrap_or(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn protected_list(names: &[&str]) -> ProtectedList {
+        ProtectedList { names: names.iter().map(|name| name.to_string()).collect(), own_ancestors: Vec::new(), own_unit: None }
+    }
+
+    #[test]
+    fn matches_names_cut_to_comm_length() {
+        let list = protected_list(&["systemd-resolved", "my-very-long-daemon-name", "sshd"]);
+        assert_eq!(
+            list.reason(4_000_000, "systemd-resolve"),
+            Some("systemd-resolved is on the protected list".to_string())
+        );
+        assert!(list.reason(4_000_000, "my-very-long-da").is_some());
+        assert!(list.reason(4_000_000, "sshd").is_some());
+        // Only a name the kernel actually cut stands for a longer one
+        assert!(list.reason(4_000_000, "systemd-resol").is_none());
+        assert!(list.reason(4_000_000, "ssh").is_none());
+    }
+
+    #[test]
+    fn applies_config() {
+        let mut names = vec!["sshd".to_string(), "cupsd".to_string()];
+        apply_config(&mut names, "# local services\npostgres\n!cupsd  # printing is fine\nsshd\n");
+        assert_eq!(names, vec!["sshd".to_string(), "postgres".to_string()]);
+    }
+}
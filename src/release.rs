@@ -0,0 +1,187 @@
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::docker::{ContainerSnapshot, Engine, EngineError, Snapshots};
+use crate::process_table::ProcessTable;
+use crate::{
+    get_processes_using_ports, is_stale_socket, procfs, published_port_rows, resolve_and_enrich,
+    HolderRole, PortSpec, ProcessInfo, Protocol, ScanOptions,
+};
+
+/// How long to wait for the listener to go away after the kill
+const RELEASE_TIMEOUT: Duration = Duration::from_secs(5);
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+/// How long to keep watching a freed port, to catch supervisors respawning the process
+const SETTLE_TIME: Duration = Duration::from_secs(1);
+
+/// Re-scans after a kill until nothing listens on `port` any more, then reports how long
+/// that took, or which processes still hold it: a child that inherited the socket, a
+/// respawned process, or the killed process itself still shutting down. Ports published
+/// by a container without a host process are watched through the engine instead. Returns
+/// whether the port was released.
+pub(crate) fn verify_port_released(
+    port: &PortSpec,
+    options: &ScanOptions,
+    targets: &[ProcessInfo],
+    killed: &[u32],
+    started: Instant,
+) -> bool {
+    // With `userland-proxy: false` the port is forwarded with iptables alone, so there is no
+    // socket to watch disappear, only the containers publishing it
+    let mut engines: Vec<Engine> = Vec::new();
+    for engine in targets.iter().filter(|target| target.is_container_only()).filter_map(|target| target.container_engine) {
+        if !engines.contains(&engine) {
+            engines.push(engine);
+        }
+    }
+
+    let Some(mut remaining) = wait_for_release(port, options, &engines, started) else {
+        return true;
+    };
+
+    resolve_and_enrich(&mut remaining, options, &mut Snapshots::default());
+    let kill_time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.saturating_sub(started.elapsed()).as_secs())
+        .unwrap_or(0);
+    for process in &remaining {
+        if process.is_container_only() {
+            let engine = process.container_engine.map_or("Docker", Engine::name);
+            println!("✗ {} is still published by {} container {} ({})", port.describe(), engine, process.process_name, process.docker_image);
+            continue;
+        }
+        if process.is_hidden() {
+            println!("✗ {} is still held by a process hidden from this user", port.describe());
+            continue;
+        }
+        match describe_relation(process, targets, killed, kill_time) {
+            Some(relation) => println!("✗ {} is still held by PID {} ({})", port.describe(), process.pid, relation),
+            None => println!("✗ {} is still held by PID {} ({})", port.describe(), process.pid, process.process_name),
+        }
+    }
+    false
+}
+
+/// Polls until the port is free and stays free for a moment. Returns the listeners (and
+/// containers of `engines` publishing the port) still or again holding it otherwise.
+fn wait_for_release(port: &PortSpec, options: &ScanOptions, engines: &[Engine], started: Instant) -> Option<Vec<ProcessInfo>> {
+    let scan = || match scan_port(port, options, engines) {
+        Ok(remaining) => Some(remaining),
+        Err(e) => {
+            println!("⚠ Can't verify that {} was released: {}", port.describe(), e);
+            None
+        }
+    };
+
+    let deadline = Instant::now() + RELEASE_TIMEOUT;
+    loop {
+        let remaining = scan()?;
+        if remaining.is_empty() {
+            break;
+        }
+        if Instant::now() >= deadline {
+            return Some(remaining);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    let freed_after = started.elapsed();
+    let settle_deadline = Instant::now() + SETTLE_TIME;
+    while Instant::now() < settle_deadline {
+        thread::sleep(POLL_INTERVAL);
+        let respawned = scan()?;
+        if !respawned.is_empty() {
+            println!("⚠ {} was free after {:.1}s, but something is listening on it again", port.describe(), freed_after.as_secs_f64());
+            return Some(respawned);
+        }
+    }
+
+    report_released(port, freed_after);
+    None
+}
+
+/// Listeners on the port, without process details: those are only needed at the end. Then
+/// the containers of `engines` still publishing it, from a fresh snapshot each time.
+fn scan_port(port: &PortSpec, options: &ScanOptions, engines: &[Engine]) -> Result<Vec<ProcessInfo>, EngineError> {
+    let processes = if port.is_unix() {
+        procfs::scan_unix_listeners().unwrap_or_default()
+    } else {
+        get_processes_using_ports(options)
+    };
+    let mut remaining: Vec<ProcessInfo> = processes.into_iter().filter(|process| port.matches(process)).collect();
+
+    let snapshots = engines
+        .iter()
+        .map(|&engine| ContainerSnapshot::load(engine, true))
+        .collect::<Result<Vec<_>, _>>()?;
+    let snapshots: Vec<&ContainerSnapshot> = snapshots.iter().collect();
+    remaining.extend(published_port_rows(port, &[], &snapshots));
+    Ok(remaining)
+}
+
+fn report_released(port: &PortSpec, elapsed: Duration) {
+    println!("✓ {} is now free after {:.1}s", port.describe(), elapsed.as_secs_f64());
+
+    if let Some(path) = port.unix_path().filter(|path| is_stale_socket(path)) {
+        println!("  The socket file is left behind, remove it with: apps-ports -k {}", path);
+    }
+
+    if port.protocol.is_none_or(|protocol| protocol == Protocol::Tcp) {
+        let time_wait = count_time_wait(port);
+        if time_wait > 0 {
+            let noun = if time_wait == 1 { "connection" } else { "connections" };
+            println!(
+                "  {} {} still in TIME_WAIT; servers that don't set SO_REUSEADDR may fail to bind for up to a minute",
+                time_wait, noun
+            );
+        }
+    }
+}
+
+fn count_time_wait(port: &PortSpec) -> usize {
+    procfs::read_time_wait_sockets()
+        .unwrap_or_default()
+        .iter()
+        .filter(|socket| port.matches_socket(socket.address, socket.port))
+        .count()
+}
+
+/// Explains how a process still holding the port relates to what was killed
+fn describe_relation(process: &ProcessInfo, targets: &[ProcessInfo], killed: &[u32], kill_time: u64) -> Option<String> {
+    let pid: u32 = process.pid.parse().ok()?;
+    if killed.contains(&pid) {
+        return Some("the killed process, still running".to_string());
+    }
+
+    // Workers inherited the socket from a killed parent, which may be gone by now
+    let was_worker = targets
+        .iter()
+        .flat_map(|target| &target.holders)
+        .any(|holder| holder.pid == process.pid && holder.role == HolderRole::Worker);
+    if was_worker {
+        return Some("child of killed process".to_string());
+    }
+
+    // Walk up the parents: children of a killed process that outlive it get reparented,
+    // but those that were started by something we didn't kill show who restarts them
+    let table = ProcessTable::load(&[pid]);
+    let meta = table.get(pid)?;
+    let mut parent = meta.ppid;
+    for _ in 0..64 {
+        let Some(ancestor) = parent.filter(|&ancestor| ancestor > 1) else {
+            break;
+        };
+        if killed.contains(&ancestor) {
+            return Some("child of killed process".to_string());
+        }
+        parent = ProcessTable::load(&[ancestor]).get(ancestor).and_then(|meta| meta.ppid);
+    }
+
+    if meta.start_time.is_some_and(|start_time| start_time >= kill_time) {
+        let parent = meta.ppid?;
+        let parent_name = ProcessTable::load(&[parent]).get(parent).map(|meta| meta.comm.clone())?;
+        return Some(format!("{}, restarted by {} (PID {})", process.process_name, parent_name, parent));
+    }
+
+    None
+}
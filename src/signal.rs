@@ -0,0 +1,262 @@
+use std::fmt;
+use std::fs;
+use std::io;
+#[cfg(target_os = "linux")]
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::process_table::{ProcessTable, StartTime};
+
+/// A signal to send to a process. The numbers used here are the same on Linux and macOS.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Signal {
+    pub number: i32,
+    pub name: &'static str,
+}
+
+pub const SIGHUP: Signal = Signal { number: 1, name: "SIGHUP" };
+pub const SIGINT: Signal = Signal { number: 2, name: "SIGINT" };
+pub const SIGQUIT: Signal = Signal { number: 3, name: "SIGQUIT" };
+pub const SIGKILL: Signal = Signal { number: 9, name: "SIGKILL" };
+pub const SIGTERM: Signal = Signal { number: 15, name: "SIGTERM" };
+
+/// Signal 0 only checks whether the process exists and could be signalled
+const PROBE: Signal = Signal { number: 0, name: "" };
+
+const NAMED_SIGNALS: [Signal; 5] = [SIGHUP, SIGINT, SIGQUIT, SIGKILL, SIGTERM];
+
+impl Signal {
+    /// Parses --signal values: TERM, SIGTERM, term, or a signal number such as 9
+    pub fn parse(value: &str) -> Result<Signal, String> {
+        if let Ok(number) = value.parse::<i32>() {
+            if !(1..=64).contains(&number) {
+                return Err(format!("invalid signal number {}", number));
+            }
+            let named = NAMED_SIGNALS.into_iter().find(|signal| signal.number == number);
+            return Ok(named.unwrap_or(Signal { number, name: "" }));
+        }
+
+        let name = value.to_uppercase();
+        let name = name.strip_prefix("SIG").unwrap_or(&name);
+        NAMED_SIGNALS
+            .into_iter()
+            .find(|signal| &signal.name[3..] == name)
+            .ok_or_else(|| format!("unknown signal '{}', expected TERM, INT, HUP, QUIT, KILL or a number", value))
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.name.is_empty() {
+            write!(f, "signal {}", self.number)
+        } else {
+            f.write_str(self.name)
+        }
+    }
+}
+
+/// Why a signal couldn't be sent, told apart so callers can say what really happened
+pub enum SignalError {
+    /// ESRCH: the process has already exited
+    NoSuchProcess,
+    /// EPERM: the process belongs to another user
+    NotPermitted,
+    /// The process has exited and its PID now belongs to another one
+    Replaced,
+    Other(io::Error),
+}
+
+impl fmt::Display for SignalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignalError::NoSuchProcess => f.write_str("no such process"),
+            SignalError::NotPermitted => f.write_str("operation not permitted"),
+            SignalError::Replaced => f.write_str("the PID now belongs to a different process"),
+            SignalError::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Sends `signal` to `pid` with kill(2)
+#[cfg(unix)]
+fn send_signal(pid: u32, signal: Signal) -> Result<(), SignalError> {
+    // kill(2) treats 0 and negative PIDs as process groups, never let those through
+    let pid = match libc::pid_t::try_from(pid) {
+        Ok(pid) if pid > 0 => pid,
+        _ => return Err(SignalError::Other(io::Error::new(io::ErrorKind::InvalidInput, "invalid PID"))),
+    };
+
+    // SAFETY: kill has no memory-safety preconditions
+    if unsafe { libc::kill(pid, signal.number) } == 0 {
+        return Ok(());
+    }
+    Err(last_signal_error())
+}
+
+#[cfg(not(unix))]
+fn send_signal(_pid: u32, _signal: Signal) -> Result<(), SignalError> {
+    Err(SignalError::Other(io::Error::new(io::ErrorKind::Unsupported, "signals are only supported on Unix")))
+}
+
+/// Sends `signal` to every process in the group `pgid` with killpg(2)
+#[cfg(unix)]
+pub fn send_signal_to_group(pgid: u32, signal: Signal) -> Result<(), SignalError> {
+    // Group 1 is init's, and killpg(0) would mean our own group
+    let pgid = match libc::pid_t::try_from(pgid) {
+        Ok(pgid) if pgid > 1 => pgid,
+        _ => return Err(SignalError::Other(io::Error::new(io::ErrorKind::InvalidInput, "invalid process group"))),
+    };
+
+    // SAFETY: killpg has no memory-safety preconditions
+    if unsafe { libc::killpg(pgid, signal.number) } == 0 {
+        return Ok(());
+    }
+    Err(last_signal_error())
+}
+
+/// Tells apart the errno values kill(2) and killpg(2) fail with
+#[cfg(unix)]
+fn last_signal_error() -> SignalError {
+    let error = io::Error::last_os_error();
+    match error.raw_os_error() {
+        Some(libc::ESRCH) => SignalError::NoSuchProcess,
+        Some(libc::EPERM) => SignalError::NotPermitted,
+        _ => SignalError::Other(error),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn send_signal_to_group(_pgid: u32, _signal: Signal) -> Result<(), SignalError> {
+    Err(SignalError::Other(io::Error::new(io::ErrorKind::Unsupported, "signals are only supported on Unix")))
+}
+
+/// A process found by the scan, pinned down so that another process reusing its PID
+/// (while the prompt waits for an answer, or during the grace period) is never signalled
+/// by mistake. On Linux 5.3+ it holds a pidfd, which keeps referring to the original
+/// process; elsewhere the start time is checked again before every signal.
+pub struct Target {
+    pub pid: u32,
+    /// As seen by the scan
+    started: Option<StartTime>,
+    #[cfg(target_os = "linux")]
+    pidfd: Option<OwnedFd>,
+}
+
+impl Target {
+    /// Pins `pid`, which the scan saw starting at `started`. Fails with `Replaced` when
+    /// the PID has been reused since.
+    pub fn open(pid: u32, started: Option<StartTime>) -> Result<Target, SignalError> {
+        let target = Target {
+            pid,
+            started,
+            #[cfg(target_os = "linux")]
+            pidfd: open_pidfd(pid)?,
+        };
+        // Checked after opening the pidfd, so that it's known to refer to the scanned process
+        target.check_identity()?;
+        Ok(target)
+    }
+
+    /// Sends `signal`, through the pidfd where there is one
+    pub fn send(&self, signal: Signal) -> Result<(), SignalError> {
+        #[cfg(target_os = "linux")]
+        if let Some(pidfd) = &self.pidfd {
+            return pidfd_send_signal(pidfd, signal);
+        }
+        self.check_identity()?;
+        send_signal(self.pid, signal)
+    }
+
+    /// Checks whether the process could be signalled, without sending anything
+    pub fn probe(&self) -> Result<(), SignalError> {
+        self.send(PROBE)
+    }
+
+    /// Whether the process still exists. Zombies count as gone: they've exited and
+    /// released their sockets, only their parent hasn't collected the exit status yet.
+    pub fn is_alive(&self) -> bool {
+        match self.probe() {
+            Ok(()) | Err(SignalError::NotPermitted) => !is_zombie(self.pid),
+            Err(_) => false,
+        }
+    }
+
+    /// Polls until the process exits, returning how long that took, or `None` on timeout
+    pub fn wait_for_exit(&self, timeout: Duration) -> Option<Duration> {
+        let start = Instant::now();
+        loop {
+            if !self.is_alive() {
+                return Some(start.elapsed());
+            }
+            if start.elapsed() >= timeout {
+                return None;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    fn check_identity(&self) -> Result<(), SignalError> {
+        let Some(expected) = self.started else {
+            return Ok(());
+        };
+        let table = ProcessTable::load(&[self.pid]);
+        let Some(meta) = table.get(self.pid) else {
+            return Err(SignalError::NoSuchProcess);
+        };
+        match meta.started {
+            Some(actual) if !expected.matches(actual) => Err(SignalError::Replaced),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Opens a pidfd, or returns `None` on kernels without pidfd_open (before 5.3)
+#[cfg(target_os = "linux")]
+fn open_pidfd(pid: u32) -> Result<Option<OwnedFd>, SignalError> {
+    let pid = libc::pid_t::try_from(pid)
+        .map_err(|_| SignalError::Other(io::Error::new(io::ErrorKind::InvalidInput, "invalid PID")))?;
+
+    // SAFETY: pidfd_open takes no pointers; on success it returns a new file descriptor we own
+    let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid, 0) };
+    if fd >= 0 {
+        // SAFETY: the descriptor was just opened and nothing else owns it
+        return Ok(Some(unsafe { OwnedFd::from_raw_fd(fd as RawFd) }));
+    }
+    match last_signal_error() {
+        SignalError::NoSuchProcess => Err(SignalError::NoSuchProcess),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn pidfd_send_signal(pidfd: &OwnedFd, signal: Signal) -> Result<(), SignalError> {
+    // SAFETY: a null siginfo is allowed and means the same as kill(2) would send
+    let result = unsafe {
+        libc::syscall(libc::SYS_pidfd_send_signal, pidfd.as_raw_fd(), signal.number, std::ptr::null::<libc::siginfo_t>(), 0)
+    };
+    if result == 0 {
+        return Ok(());
+    }
+    Err(last_signal_error())
+}
+
+fn is_zombie(pid: u32) -> bool {
+    // The state follows the parenthesized command name in /proc/<pid>/stat
+    fs::read_to_string(format!("/proc/{}/stat", pid))
+        .ok()
+        .and_then(|stat| Some(stat.rsplit_once(')')?.1.trim_start().starts_with('Z')))
+        .unwrap_or(false)
+}
+
+/// Polls until every target exits, returning those still running at the timeout
+pub fn wait_for_all(targets: &[Target], timeout: Duration) -> Vec<&Target> {
+    let start = Instant::now();
+    loop {
+        let running: Vec<&Target> = targets.iter().filter(|target| target.is_alive()).collect();
+        if running.is_empty() || start.elapsed() >= timeout {
+            return running;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+}
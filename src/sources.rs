@@ -0,0 +1,625 @@
+use std::process::{Command as StdCommand, Stdio};
+
+use crate::docker::Snapshots;
+use crate::{
+    create_shared_process_info, enrich_processes, format_endpoint, hidden_process_info, normalize_host, procfs, same_address, split_host_port, AddressFamily, Backend, PortSpec, ProcessInfo, Protocol,
+    Sighting,
+};
+
+/// A way of discovering listening sockets and the processes holding them
+pub(crate) trait PortSource {
+    /// Short name recorded on every row the source reports, e.g. "ss"
+    fn name(&self) -> &'static str;
+
+    /// Lists every listener the source can see, or `None` when the source isn't
+    /// available here (command not installed, no /proc, unsupported platform)
+    fn scan(&self) -> Option<Vec<ProcessInfo>>;
+
+    /// Looks up the processes holding a socket that no scan could attribute
+    fn find_owner(&self, _socket: &ProcessInfo) -> Option<ProcessInfo> {
+        None
+    }
+}
+
+/// NETLINK_SOCK_DIAG, asking the kernel for its socket tables directly
+struct NetlinkSource;
+
+/// /proc/net/{tcp,udp}{,6}, with owners found through /proc/<pid>/fd
+struct ProcSource;
+
+struct SsSource;
+
+struct NetstatSource;
+
+struct LsofSource;
+
+/// fuser can only answer for one port at a time, so it never scans
+struct FuserSource;
+
+impl PortSource for NetlinkSource {
+    fn name(&self) -> &'static str {
+        "netlink"
+    }
+
+    #[cfg(target_os = "linux")]
+    fn scan(&self) -> Option<Vec<ProcessInfo>> {
+        crate::netlink::scan_listeners()
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn scan(&self) -> Option<Vec<ProcessInfo>> {
+        None
+    }
+}
+
+impl PortSource for ProcSource {
+    fn name(&self) -> &'static str {
+        "proc"
+    }
+
+    fn scan(&self) -> Option<Vec<ProcessInfo>> {
+        procfs::scan_listeners()
+    }
+}
+
+impl PortSource for SsSource {
+    fn name(&self) -> &'static str {
+        "ss"
+    }
+
+    fn scan(&self) -> Option<Vec<ProcessInfo>> {
+        // Try ss with process info (requires elevated privileges for some processes)
+        for args in [
+            ["--tcp", "--udp", "--listening", "--numeric", "--extended", "--processes"].as_slice(),
+            ["--tcp", "--udp", "--listening", "--numeric", "--extended"].as_slice(),
+        ] {
+            let output = StdCommand::new("ss")
+                .args(args)
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .output();
+
+            if let Ok(output) = output {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let mut processes = Vec::new();
+
+                for line in stdout.lines().skip(1) { // Skip header
+                    if let Some(process_info) = parse_ss_line(line) {
+                        processes.push(process_info);
+                    }
+                }
+
+                if !processes.is_empty() {
+                    return Some(processes);
+                }
+            }
+        }
+        None
+    }
+}
+
+impl PortSource for NetstatSource {
+    fn name(&self) -> &'static str {
+        "netstat"
+    }
+
+    fn scan(&self) -> Option<Vec<ProcessInfo>> {
+        // -e adds the User and Inode columns
+        let output = StdCommand::new("netstat")
+            .args(["-tulnpe"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let processes = stdout.lines()
+            // UDP sockets have no state column, so they can't be matched on LISTEN
+            .filter(|line| line.starts_with("udp") || (line.starts_with("tcp") && line.contains("LISTEN")))
+            .filter_map(parse_netstat_line)
+            .collect();
+        Some(processes)
+    }
+}
+
+impl PortSource for LsofSource {
+    fn name(&self) -> &'static str {
+        "lsof"
+    }
+
+    fn scan(&self) -> Option<Vec<ProcessInfo>> {
+        let mut sockets: Vec<LsofSocket> = Vec::new();
+        let mut found = false;
+
+        // The state filter only applies to TCP, and restricting to it hides UDP entirely
+        for args in [["-iTCP", "-sTCP:LISTEN", "-P", "-n"].as_slice(), ["-iUDP", "-P", "-n"].as_slice()] {
+            let Ok(output) = StdCommand::new("lsof")
+                .args(args)
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .output()
+            else {
+                continue;
+            };
+            found = true;
+
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            sockets.extend(stdout.lines().skip(1).filter_map(parse_lsof_line)); // Skip header
+        }
+
+        found.then(|| group_lsof_sockets(sockets))
+    }
+
+    fn find_owner(&self, socket: &ProcessInfo) -> Option<ProcessInfo> {
+        let output = StdCommand::new("lsof")
+            .args(["-i", &format!("{}:{}", socket.protocol, socket.port), "-P", "-n"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let sockets: Vec<LsofSocket> = stdout.lines()
+            .skip(1)
+            // UDP sockets have no LISTEN state; parse_lsof_line skips connected ones
+            .filter(|line| socket.protocol == Protocol::Udp || line.contains("LISTEN"))
+            .filter_map(parse_lsof_line)
+            // Several sockets can share a port on different addresses
+            .filter(|found| same_address(&found.address, &socket.address))
+            .collect();
+        group_lsof_sockets(sockets).into_iter().next()
+    }
+}
+
+impl PortSource for FuserSource {
+    fn name(&self) -> &'static str {
+        "fuser"
+    }
+
+    fn scan(&self) -> Option<Vec<ProcessInfo>> {
+        None
+    }
+
+    fn find_owner(&self, socket: &ProcessInfo) -> Option<ProcessInfo> {
+        // Without the socket's inode, fuser's PIDs can't be tied to this row
+        let inode = socket.inode?;
+        let output = StdCommand::new("fuser")
+            .args([&format!("{}/{}", socket.port, socket.protocol)])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+
+        // fuser lists every process using the port, on any address and connected ones too,
+        // so only those holding this very socket are kept
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let pids: Vec<u32> = stdout.split_whitespace()
+            .filter_map(|word| word.parse::<u32>().ok())
+            .filter(|&pid| procfs::holds_socket(pid, inode))
+            .collect();
+        if pids.is_empty() {
+            return None;
+        }
+
+        let mut process_info = create_shared_process_info(
+            socket.protocol,
+            socket.family,
+            socket.address.clone(),
+            socket.port.clone(),
+            &pids,
+        );
+        process_info.inode = Some(inode);
+        Some(process_info)
+    }
+}
+
+/// Sources that can list listeners, in the order --explain-sources reports them
+fn scanners() -> Vec<Box<dyn PortSource>> {
+    vec![
+        Box::new(NetlinkSource),
+        Box::new(ProcSource),
+        Box::new(SsSource),
+        Box::new(NetstatSource),
+        Box::new(LsofSource),
+    ]
+}
+
+/// Sources asked about sockets whose owner wasn't visible to the scan, best first
+fn owner_lookups() -> Vec<Box<dyn PortSource>> {
+    vec![Box::new(LsofSource), Box::new(FuserSource)]
+}
+
+fn backend_source(backend: Backend) -> Option<Box<dyn PortSource>> {
+    match backend {
+        Backend::Auto => None,
+        Backend::Netlink => Some(Box::new(NetlinkSource)),
+        Backend::Proc => Some(Box::new(ProcSource)),
+        Backend::Ss => Some(Box::new(SsSource)),
+        Backend::Netstat => Some(Box::new(NetstatSource)),
+        Backend::Lsof => Some(Box::new(LsofSource)),
+    }
+}
+
+pub(crate) fn discover_listeners(backend: Backend) -> Vec<ProcessInfo> {
+    match backend_source(backend) {
+        Some(source) => scan_source(source.as_ref()).unwrap_or_default(),
+        None => scan_auto(),
+    }
+}
+
+fn scan_auto() -> Vec<ProcessInfo> {
+    // Ask the kernel directly when possible, it's much faster than parsing text
+    // and sees every socket regardless of who owns it
+    for source in [&NetlinkSource as &dyn PortSource, &ProcSource] {
+        if let Some(processes) = scan_source(source) {
+            return processes;
+        }
+    }
+
+    // Otherwise combine what ss, netstat and lsof each manage to see
+    let mut processes = Vec::new();
+    for source in [&SsSource as &dyn PortSource, &NetstatSource, &LsofSource] {
+        for process_info in scan_source(source).into_iter().flatten() {
+            merge_row(&mut processes, process_info);
+        }
+    }
+    processes
+}
+
+/// Runs one source's scan and records it as the provenance of every row
+fn scan_source(source: &dyn PortSource) -> Option<Vec<ProcessInfo>> {
+    let mut processes = source.scan()?;
+    for process_info in &mut processes {
+        process_info.sources.push(Sighting { source: source.name(), pid: process_info.pid.clone(), lookup: false });
+    }
+    Some(processes)
+}
+
+/// Adds a row unless another source already reported the same socket. In that case
+/// the sightings are combined, and a hidden row adopts the owner the new source found.
+fn merge_row(processes: &mut Vec<ProcessInfo>, process_info: ProcessInfo) {
+    let Some(existing) = processes.iter_mut().find(|existing| is_same_socket(existing, &process_info)) else {
+        processes.push(process_info);
+        return;
+    };
+
+    existing.sources.extend(process_info.sources.iter().cloned());
+    existing.inode = existing.inode.or(process_info.inode);
+    existing.uid = existing.uid.or(process_info.uid);
+    if existing.is_hidden() && !process_info.is_hidden() {
+        adopt_owner(existing, process_info);
+    }
+}
+
+fn is_same_socket(a: &ProcessInfo, b: &ProcessInfo) -> bool {
+    a.protocol == b.protocol
+        && a.port == b.port
+        && same_address(&a.address, &b.address)
+        // Not every source knows the inode, but when both do they have to agree
+        && match (a.inode, b.inode) {
+            (Some(a), Some(b)) => a == b,
+            _ => true,
+        }
+}
+
+pub(crate) fn adopt_owner(row: &mut ProcessInfo, owner: ProcessInfo) {
+    row.pid = owner.pid;
+    row.holders = owner.holders;
+    row.process_name = owner.process_name;
+    row.command = owner.command;
+    row.docker_container_id = owner.docker_container_id;
+    row.docker_image = owner.docker_image;
+    row.docker_labels = owner.docker_labels;
+    row.container_engine = owner.container_engine;
+    row.pod = owner.pod;
+    row.compose_project = owner.compose_project;
+    row.compose_service = owner.compose_service;
+    row.compose_dir = owner.compose_dir;
+}
+
+/// Asks lsof, then fuser, about the hidden rows of the auto mode's ss, netstat and lsof
+/// fallback, once they are filtered down to the ones to show. Rows from netlink or /proc
+/// are left hidden: both tools read the same /proc/<pid>/fd entries the scan walked.
+pub(crate) fn look_up_hidden_owners(processes: &mut [ProcessInfo]) {
+    let lookups = owner_lookups();
+    for process_info in processes.iter_mut() {
+        let scanned_by_kernel = process_info
+            .sources
+            .iter()
+            .any(|sighting| !sighting.lookup && matches!(sighting.source, "netlink" | "proc"));
+        if !scanned_by_kernel {
+            resolve_hidden_owner(process_info, &lookups, false);
+        }
+    }
+}
+
+/// Asks lsof, then fuser, about sockets the scan couldn't attribute to a process.
+/// With `ask_all`, every lookup is consulted even after one has found the owner.
+fn resolve_hidden_owners(processes: &mut [ProcessInfo], ask_all: bool) {
+    let lookups = owner_lookups();
+    for process_info in processes.iter_mut() {
+        resolve_hidden_owner(process_info, &lookups, ask_all);
+    }
+}
+
+fn resolve_hidden_owner(process_info: &mut ProcessInfo, lookups: &[Box<dyn PortSource>], ask_all: bool) {
+    // Unix sockets have no port to look up by
+    if !process_info.is_hidden() || process_info.protocol == Protocol::Unix {
+        return;
+    }
+
+    for lookup in lookups {
+        let Some(owner) = lookup.find_owner(process_info) else {
+            continue;
+        };
+        process_info.sources.push(Sighting { source: lookup.name(), pid: owner.pid.clone(), lookup: true });
+        if process_info.is_hidden() {
+            adopt_owner(process_info, owner);
+        }
+        if !ask_all {
+            break;
+        }
+    }
+}
+
+/// Runs every source, merges their rows and prints what each one reported for every
+/// socket in our network namespace, to show why a row is hidden or which tool disagrees
+pub(crate) fn explain_sources(port: Option<&PortSpec>, json_output: bool) {
+    let mut available = Vec::new();
+    let mut processes: Vec<ProcessInfo> = Vec::new();
+    for scanner in scanners() {
+        let rows = scan_source(scanner.as_ref());
+        available.push((scanner.name(), rows.is_some()));
+        for process_info in rows.into_iter().flatten() {
+            merge_row(&mut processes, process_info);
+        }
+    }
+
+    if let Some(port) = port {
+        processes.retain(|process| port.matches(process));
+    }
+    let looked_up: Vec<bool> = processes.iter().map(|process| process.is_hidden()).collect();
+    resolve_hidden_owners(&mut processes, true);
+    enrich_processes(&mut processes, &mut Snapshots::default());
+
+    if json_output {
+        match serde_json::to_string_pretty(&processes) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("Error serializing to JSON: {}", e),
+        }
+        return;
+    }
+
+    println!("Sources:");
+    for (name, is_available) in &available {
+        if *is_available {
+            println!("  ✓ {}", name);
+        } else {
+            println!("  ✗ {} (unavailable)", name);
+        }
+    }
+    println!();
+
+    if processes.is_empty() {
+        match port {
+            Some(port) => println!("No source reported {}", port.describe()),
+            None => println!("No source reported any listening ports"),
+        }
+        return;
+    }
+
+    for (process, looked_up) in processes.iter().zip(looked_up) {
+        if process.is_hidden() {
+            println!("{}  hidden", format_endpoint(process));
+        } else {
+            println!("{}  {} ({})", format_endpoint(process), process.pid, process.process_name);
+        }
+        for (name, is_available) in &available {
+            let sighting = process.sources.iter().find(|sighting| sighting.source == *name && !sighting.lookup);
+            let status = match sighting {
+                Some(sighting) => describe_sighting(sighting, process),
+                None if *is_available => "not reported".to_string(),
+                None => "unavailable".to_string(),
+            };
+            println!("  {:<8} {}", name, status);
+        }
+        // Lookups by port only run for sockets that every scan left hidden
+        if looked_up {
+            for lookup in owner_lookups() {
+                let sighting = process.sources.iter().find(|sighting| sighting.source == lookup.name() && sighting.lookup);
+                let status = match sighting {
+                    Some(sighting) => describe_sighting(sighting, process),
+                    None => "no owner found".to_string(),
+                };
+                println!("  {:<8} {} (looked up by port)", lookup.name(), status);
+            }
+        }
+        println!();
+    }
+}
+
+fn describe_sighting(sighting: &Sighting, process: &ProcessInfo) -> String {
+    if sighting.pid == "hidden" {
+        "hidden (owner not visible to this source)".to_string()
+    } else if !process.holders.iter().any(|holder| holder.pid == sighting.pid) {
+        format!("pid {} (disagrees)", sighting.pid)
+    } else {
+        format!("pid {}", sighting.pid)
+    }
+}
+
+fn parse_ss_line(line: &str) -> Option<ProcessInfo> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+
+    // Listing more than one socket type adds a leading Netid column
+    let protocol = Protocol::from_name(parts.first()?)?;
+    let parts = &parts[1..];
+    let mut process_info = parse_ss_process(protocol, parts)?;
+
+    // For listeners Recv-Q is the current accept backlog and Send-Q its maximum
+    process_info.recv_q = parts.get(1).and_then(|q| q.parse().ok());
+    process_info.send_q = parts.get(2).and_then(|q| q.parse().ok());
+
+    // --extended appends "uid:1000 ino:12345 sk:...", leaving out the uid when it's root
+    if let Some(inode) = parts.iter().skip(5).find_map(|field| field.strip_prefix("ino:")) {
+        process_info.inode = inode.parse().ok();
+        process_info.uid = Some(parts.iter().skip(5).find_map(|field| field.strip_prefix("uid:")?.parse().ok()).unwrap_or(0));
+    }
+    // IPv6 sockets also get "v6only:0" or "v6only:1"
+    process_info.v6only = parts.iter().skip(5).find_map(|field| field.strip_prefix("v6only:")).map(|flag| flag != "0");
+    Some(process_info)
+}
+
+fn parse_ss_process(protocol: Protocol, parts: &[&str]) -> Option<ProcessInfo> {
+    if parts.len() >= 4 {
+        // Split address and port (format: *:8080 or 0.0.0.0:8080 or [::]:8080)
+        let (host, port) = split_host_port(parts[3])?;
+        let family = AddressFamily::from_host(host);
+        let address = normalize_host(host, family);
+        let port = port.to_string();
+
+        // Check if we have process info in the last column
+        if parts.len() >= 6 {
+            let pids = parse_ss_users(&parts[5..].join(" "));
+            if !pids.is_empty() {
+                return Some(create_shared_process_info(protocol, family, address, port, &pids));
+            }
+        }
+
+        return Some(hidden_process_info(protocol, family, address, port));
+    }
+    None
+}
+
+/// Extracts every holder PID from users:(("nginx",pid=1,fd=6),("nginx",pid=2,fd=6))
+fn parse_ss_users(column: &str) -> Vec<u32> {
+    let mut pids = Vec::new();
+    for entry in column.split("pid=").skip(1) {
+        let digits: String = entry.chars().take_while(|c| c.is_ascii_digit()).collect();
+        if let Ok(pid) = digits.parse::<u32>() {
+            // A process holding the socket through several fds is listed once per fd
+            if !pids.contains(&pid) {
+                pids.push(pid);
+            }
+        }
+    }
+    pids
+}
+
+fn parse_netstat_line(line: &str) -> Option<ProcessInfo> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() < 8 {
+        return None;
+    }
+
+    let protocol = Protocol::from_name(parts[0])?;
+    let family = if parts[0].ends_with('6') { AddressFamily::Ipv6 } else { AddressFamily::Ipv4 };
+    let (host, port) = split_host_port(parts[3])?;
+    let address = normalize_host(host, family);
+
+    // UDP lines leave the state column empty, and program names may contain spaces,
+    // so find PID/Program ("1234/nginx", or "-" when hidden) and read User and Inode before it
+    let pid_column = (6..parts.len()).find(|&i| {
+        parts[i] == "-" || parts[i].split_once('/').is_some_and(|(pid, _)| pid.parse::<u32>().is_ok())
+    })?;
+    let pid = parts[pid_column].split_once('/').and_then(|(pid, _)| pid.parse::<u32>().ok());
+    let mut process_info = match pid {
+        Some(pid) => create_shared_process_info(protocol, family, address, port.to_string(), &[pid]),
+        None => hidden_process_info(protocol, family, address, port.to_string()),
+    };
+    process_info.uid = parts[pid_column - 2].parse().ok();
+    process_info.inode = parts[pid_column - 1].parse().ok();
+    Some(process_info)
+}
+
+/// Parses `lsof -i` output into one row per listening TCP or bound UDP socket
+pub(crate) fn parse_lsof_output(output: &str) -> Vec<ProcessInfo> {
+    let sockets = output.lines()
+        .skip(1) // Skip header
+        .filter(|line| line.contains("UDP") || line.contains("(LISTEN)"))
+        .filter_map(parse_lsof_line)
+        .collect();
+    group_lsof_sockets(sockets)
+}
+
+/// A socket line printed by lsof, before process details are looked up
+struct LsofSocket {
+    protocol: Protocol,
+    family: AddressFamily,
+    address: String,
+    port: String,
+    device: String,
+    pid: u32,
+}
+
+impl LsofSocket {
+    fn is_same_socket(&self, other: &LsofSocket) -> bool {
+        // DEVICE identifies the socket itself (the inode on Linux), shared by every holder
+        self.device == other.device
+            && self.protocol == other.protocol
+            && self.port == other.port
+            && same_address(&self.address, &other.address)
+    }
+}
+
+/// Merges lsof's one-line-per-file-descriptor output into one row per socket
+fn group_lsof_sockets(sockets: Vec<LsofSocket>) -> Vec<ProcessInfo> {
+    let mut groups: Vec<(LsofSocket, Vec<u32>)> = Vec::new();
+    for socket in sockets {
+        match groups.iter_mut().find(|(first, _)| first.is_same_socket(&socket)) {
+            Some((_, pids)) => {
+                if !pids.contains(&socket.pid) {
+                    pids.push(socket.pid);
+                }
+            }
+            None => {
+                let pid = socket.pid;
+                groups.push((socket, vec![pid]));
+            }
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(socket, pids)| {
+            let mut process_info = create_shared_process_info(
+                socket.protocol,
+                socket.family,
+                socket.address,
+                socket.port,
+                &pids,
+            );
+            process_info.inode = socket.device.parse().ok();
+            process_info
+        })
+        .collect()
+}
+
+fn parse_lsof_line(line: &str) -> Option<LsofSocket> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() >= 9 {
+        let pid = parts[1].parse::<u32>().ok()?;
+        let family = if parts[4] == "IPv6" { AddressFamily::Ipv6 } else { AddressFamily::Ipv4 };
+        let protocol = Protocol::from_name(parts[7])?;
+        let address = parts[8];
+
+        // Connected UDP sockets show "local->remote"; they aren't listeners
+        if address.contains("->") {
+            return None;
+        }
+
+        if let Some((host, port_part)) = split_host_port(address) {
+            if let Some(port) = port_part.split('(').next() {
+                return Some(LsofSocket {
+                    protocol,
+                    family,
+                    address: normalize_host(host, family),
+                    port: port.to_string(),
+                    device: parts[5].to_string(),
+                    pid,
+                });
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,207 @@
+use std::fmt;
+use std::fs;
+use std::process::{Command as StdCommand, Stdio};
+
+use crate::{in_host_namespace, privileged, same_address, same_socket_path, ProcessInfo, Protocol};
+
+/// A systemd unit managing a listener. Killing its process only makes systemd restart
+/// it, so -k offers `systemctl stop` instead.
+#[derive(Clone, serde::Serialize)]
+pub(crate) struct SystemdUnit {
+    pub name: String,
+    /// Managed by the user's own systemd instance, stopped with `systemctl --user`
+    pub user: bool,
+    /// For a .socket unit, where systemd itself holds the listening socket: the services
+    /// it starts when a connection comes in
+    pub activates: Vec<String>,
+}
+
+impl SystemdUnit {
+    /// Every unit to stop: a socket unit and its service both go, or the socket would
+    /// just start the service again on the next connection
+    pub fn units_to_stop(&self) -> Vec<&str> {
+        let mut units = vec![self.name.as_str()];
+        units.extend(self.activates.iter().map(String::as_str));
+        units
+    }
+}
+
+impl fmt::Display for SystemdUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+        if self.user {
+            f.write_str(" (user)")?;
+        }
+        Ok(())
+    }
+}
+
+/// The service `pid` runs in, from its cgroup path: `/system.slice/nginx.service`, or
+/// `/user.slice/user-1000.slice/user@1000.service/app.slice/vite.service` for user units.
+/// Scopes (login sessions, terminals, containers) aren't services and are left out.
+pub(crate) fn unit_for_pid(pid: u32) -> Option<SystemdUnit> {
+    let cgroup = fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    // cgroup v2 has a single "0::" line; v1 has systemd's own "name=systemd" hierarchy
+    let path = cgroup
+        .lines()
+        .find_map(|line| line.strip_prefix("0::").filter(|path| *path != "/"))
+        .or_else(|| cgroup.lines().find_map(|line| line.split_once(":name=systemd:").map(|(_, path)| path)))?;
+
+    let components: Vec<&str> = path.split('/').collect();
+    let (index, name) = components.iter().enumerate().rev().find(|(_, name)| name.ends_with(".service"))?;
+    // user@1000.service is the user's whole systemd instance, not something to stop
+    if is_user_manager(name) {
+        return None;
+    }
+    let user = components[..index].iter().any(|component| is_user_manager(component));
+    Some(SystemdUnit { name: name.to_string(), user, activates: Vec::new() })
+}
+
+fn is_user_manager(unit: &str) -> bool {
+    unit.starts_with("user@") && unit.ends_with(".service")
+}
+
+/// What systemd watches in a service: its main process, and whether it starts the service
+/// again when that process exits (`Restart=` other than `no`)
+pub(crate) struct ServiceState {
+    pub main_pid: Option<u32>,
+    pub restarts: bool,
+}
+
+/// Asks `systemctl show` for the service's main PID and restart policy. Only our own user
+/// manager answers for user units.
+pub(crate) fn service_state(unit: &SystemdUnit) -> Option<ServiceState> {
+    let mut command = StdCommand::new("systemctl");
+    if unit.user {
+        command.arg("--user");
+    }
+    let output = command.args(["show", "--property=MainPID,Restart", &unit.name]).stderr(Stdio::null()).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let property = |name: &str| stdout.lines().find_map(|line| line.strip_prefix(name)?.strip_prefix('='));
+    Some(ServiceState {
+        // 0 when the service isn't running
+        main_pid: property("MainPID").and_then(|pid| pid.parse().ok()).filter(|&pid| pid != 0),
+        restarts: property("Restart").is_some_and(|restart| restart != "no"),
+    })
+}
+
+/// Socket-activated listeners show up as held by systemd itself (PID 1, or the user's
+/// `systemd --user`). Looks up which .socket unit they belong to with
+/// `systemctl list-sockets`, run at most once per manager.
+///
+/// To other users PID 1's sockets are hidden rows. `systemctl list-sockets` needs no
+/// privilege, so those are matched against the system manager's sockets by address alone.
+pub(crate) fn attach_socket_units(processes: &mut [ProcessInfo]) {
+    let mut system_sockets: Option<Vec<ListeningSocket>> = None;
+    let mut user_sockets: Option<Vec<ListeningSocket>> = None;
+
+    for process in processes.iter_mut().filter(|process| process.unit.is_none()) {
+        let user = if process.is_hidden() {
+            // PID 1 creates its sockets as root, in the host namespace
+            if process.uid.is_some_and(|uid| uid != 0) || !in_host_namespace(process) {
+                continue;
+            }
+            false
+        } else if process.process_name == "systemd" {
+            process.pid != "1"
+        } else {
+            continue;
+        };
+        // Only our own user manager answers `systemctl --user`
+        if user && process.uid != Some(privileged::effective_uid()) {
+            continue;
+        }
+        let sockets = if user { &mut user_sockets } else { &mut system_sockets };
+        let sockets = sockets.get_or_insert_with(|| list_sockets(user));
+        if let Some(socket) = sockets.iter().find(|socket| socket.matches(process)) {
+            process.unit = Some(SystemdUnit { name: socket.unit.clone(), user, activates: socket.activates.clone() });
+        }
+    }
+}
+
+/// One line of `systemctl list-sockets`
+struct ListeningSocket {
+    listen: String,
+    unit: String,
+    activates: Vec<String>,
+}
+
+impl ListeningSocket {
+    fn matches(&self, process: &ProcessInfo) -> bool {
+        if process.protocol == Protocol::Unix {
+            return same_socket_path(&self.listen, &process.address);
+        }
+        let Some((address, port)) = self.listen.rsplit_once(':') else {
+            return false;
+        };
+        // [::]:22, or 127.0.0.53%lo:53 for sockets bound to a device
+        let address = address.trim_start_matches('[').trim_end_matches(']');
+        let address = address.split('%').next().unwrap_or(address);
+        port == process.port && same_address(address, &process.address)
+    }
+}
+
+fn list_sockets(user: bool) -> Vec<ListeningSocket> {
+    let mut command = StdCommand::new("systemctl");
+    if user {
+        command.arg("--user");
+    }
+    // Only active units hold their sockets; an inactive one's address may be bound by
+    // anything else, which a hidden row couldn't tell apart
+    let Ok(output) = command.args(["list-sockets", "--full", "--no-legend"]).stderr(Stdio::null()).output() else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout).lines().filter_map(parse_list_sockets_line).collect()
+}
+
+/// "[::]:22 ssh.socket ssh.service", where the last column may be empty or list several
+/// services
+fn parse_list_sockets_line(line: &str) -> Option<ListeningSocket> {
+    let mut fields = line.split_whitespace();
+    let listen = fields.next()?.to_string();
+    let unit = fields.next()?.to_string();
+    let activates = fields
+        .flat_map(|field| field.split(','))
+        .filter(|unit| !unit.is_empty())
+        .map(str::to_string)
+        .collect();
+    Some(ListeningSocket { listen, unit, activates })
+}
+
+/// Why `systemctl stop` failed, told apart so the caller can offer sudo
+pub(crate) enum StopError {
+    /// polkit said no, or needed a password it couldn't ask for
+    NotPermitted(String),
+    Failed(String),
+}
+
+/// Runs `systemctl stop` for the unit, and for the service behind a socket unit.
+/// polkit's password prompt is turned off, sudo is the one way to elevate.
+pub(crate) fn stop_unit(unit: &SystemdUnit, with_sudo: bool) -> Result<(), StopError> {
+    let mut command = if with_sudo {
+        let mut command = privileged::sudo_command();
+        command.arg("systemctl");
+        command
+    } else {
+        StdCommand::new("systemctl")
+    };
+    if unit.user {
+        command.arg("--user");
+    }
+    command.args(["stop", "--no-ask-password"]).args(unit.units_to_stop());
+
+    let output = command.stdin(Stdio::inherit()).output().map_err(|e| StopError::Failed(e.to_string()))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    let denied = ["Access denied", "Interactive authentication required", "Permission denied"];
+    if !with_sudo && denied.iter().any(|message| stderr.contains(message)) {
+        Err(StopError::NotPermitted(stderr))
+    } else {
+        Err(StopError::Failed(stderr))
+    }
+}